@@ -1,4 +1,3 @@
-use gdsdk;
 use std::{env, error::Error, io::Write, process};
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -15,7 +14,7 @@ fn main() -> Result<(), Box<dyn Error>> {
                 // write gds data back
                 let gds_bytes = lib.gds_bytes()?;
                 let mut file = std::fs::File::create("new.gds")?;
-                file.write(&gds_bytes)?;
+                file.write_all(&gds_bytes)?;
             }
             Err(err) => eprintln!("parse file {} error: {}", file, err),
         }
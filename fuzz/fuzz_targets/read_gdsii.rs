@@ -0,0 +1,28 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// reading any bytes should return Ok or Err, never panic
+fuzz_target!(|data: &[u8]| {
+    let _ = gdsdk::read_gdsii_from(data);
+
+    let lenient = gdsdk::ReadOptions {
+        strict: false,
+        ..Default::default()
+    };
+    let _ = gdsdk::read_gdsii_from_with_options(data, &lenient);
+
+    // lazy readers only read files, index and decode the same bytes from a file
+    let file = std::env::temp_dir().join(format!("gdsdk_fuzz_{}.gds", std::process::id()));
+    std::fs::write(&file, data).unwrap();
+    for lazy_lib in [gdsdk::read_gdsii_mmap(&file), gdsdk::read_gdsii_lazy(&file)] {
+        let Ok(lazy_lib) = lazy_lib else {
+            continue;
+        };
+        for name in lazy_lib.struc_names() {
+            let _ = lazy_lib.struc(name);
+        }
+        let _ = lazy_lib.to_lib();
+    }
+    let _ = std::fs::remove_file(&file);
+});
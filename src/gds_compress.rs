@@ -0,0 +1,220 @@
+//! compressed gds data, detected by magic bytes on reading
+//!
+//! decompressors and compressors are enabled by cargo features `gzip`, `zstd` and `bzip2`
+
+use crate::gds_error::GdsError;
+use std::io::{Cursor, Read, Write};
+
+/// Compression of gds data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    /// needs `gzip` feature
+    Gzip,
+    /// needs `zstd` feature
+    Zstd,
+    /// needs `bzip2` feature
+    Bzip2,
+}
+
+impl Compression {
+    /// detect compression by magic bytes at the beginning of data
+    pub fn detect(magic: &[u8]) -> Self {
+        match magic {
+            [0x1f, 0x8b, ..] => Compression::Gzip,
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Compression::Zstd,
+            [b'B', b'Z', b'h', ..] => Compression::Bzip2,
+            _ => Compression::None,
+        }
+    }
+
+    fn feature_disabled(self) -> GdsError {
+        let feature = match self {
+            Compression::None => "",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Bzip2 => "bzip2",
+        };
+        GdsError::Io(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!(
+                "{:?} compressed gds data needs `{}` feature of gdsdk",
+                self, feature
+            ),
+        ))
+    }
+}
+
+// read magic bytes of data, return compression and reader starting from the magic bytes
+pub(crate) fn detect<'a, R: Read + 'a>(
+    mut reader: R,
+) -> Result<(Compression, impl Read + 'a), GdsError> {
+    let mut magic = [0_u8; 4];
+    let mut len = 0;
+    while len < magic.len() {
+        match reader.read(&mut magic[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let compression = Compression::detect(&magic[..len]);
+    Ok((
+        compression,
+        Cursor::new(magic).take(len as u64).chain(reader),
+    ))
+}
+
+/// wrap reader with decompressor if data is compressed
+pub(crate) fn decompress<'a, R: Read + 'a>(reader: R) -> Result<Box<dyn Read + 'a>, GdsError> {
+    let (compression, reader) = detect(reader)?;
+    match compression {
+        Compression::None => Ok(Box::new(reader)),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Ok(Box::new(flate2::read::MultiGzDecoder::new(reader))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(Box::new(zstd::stream::read::Decoder::new(reader)?)),
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Ok(Box::new(bzip2::read::MultiBzDecoder::new(reader))),
+        #[allow(unreachable_patterns)]
+        other => Err(other.feature_disabled()),
+    }
+}
+
+// writer compressing gds data
+pub(crate) enum Encoder<W: Write> {
+    Plain(W),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<W>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+    #[cfg(feature = "bzip2")]
+    Bzip2(bzip2::write::BzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    pub(crate) fn new(writer: W, compression: Compression) -> Result<Self, GdsError> {
+        match compression {
+            Compression::None => Ok(Encoder::Plain(writer)),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Ok(Encoder::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            ))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(Encoder::Zstd(zstd::stream::write::Encoder::new(writer, 0)?)),
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => Ok(Encoder::Bzip2(bzip2::write::BzEncoder::new(
+                writer,
+                bzip2::Compression::default(),
+            ))),
+            #[allow(unreachable_patterns)]
+            other => Err(other.feature_disabled()),
+        }
+    }
+
+    /// write end of compressed stream, return inner writer
+    // only plain writer is left without compression features
+    #[allow(clippy::infallible_destructuring_match)]
+    pub(crate) fn finish(self) -> Result<W, GdsError> {
+        let mut writer = match self {
+            Encoder::Plain(w) => w,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(e) => e.finish()?,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e.finish()?,
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(e) => e.finish()?,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Encoder::Plain(w) => w.write(buf),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(e) => e.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e.write(buf),
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Encoder::Plain(w) => w.flush(),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(e) => e.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e.flush(),
+            #[cfg(feature = "bzip2")]
+            Encoder::Bzip2(e) => e.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_gds_compress {
+    use super::*;
+    use crate::gds_model::Lib;
+    use crate::WriteOptions;
+
+    fn round_trip(compression: Compression) -> Result<(), GdsError> {
+        let lib = Lib::new("test");
+        let mut bytes = Vec::new();
+        let options = WriteOptions {
+            compression,
+            ..Default::default()
+        };
+        crate::write_gdsii_to(&mut bytes, &lib, &options)?;
+        assert_eq!(Compression::detect(&bytes), compression);
+        let read_lib = crate::read_gdsii_from(bytes.as_slice())?;
+        assert_eq!(read_lib.name, "test");
+        Ok(())
+    }
+
+    #[test]
+    fn test_compression_round_trip() {
+        round_trip(Compression::None).unwrap();
+        for compression in [Compression::Gzip, Compression::Zstd, Compression::Bzip2] {
+            let enabled = match compression {
+                Compression::Gzip => cfg!(feature = "gzip"),
+                Compression::Zstd => cfg!(feature = "zstd"),
+                Compression::Bzip2 => cfg!(feature = "bzip2"),
+                Compression::None => true,
+            };
+            match round_trip(compression) {
+                Ok(()) => assert!(enabled),
+                Err(GdsError::Io(e)) => {
+                    assert!(!enabled);
+                    assert_eq!(e.kind(), std::io::ErrorKind::Unsupported);
+                }
+                Err(e) => panic!("{}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_detect_compression() {
+        let gds = [0x00, 0x06, 0x00, 0x02, 0x02, 0x58];
+        let (compression, mut reader) = detect(&gds[..]).unwrap();
+        assert_eq!(compression, Compression::None);
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(read, gds);
+
+        assert_eq!(Compression::detect(&[0x1f, 0x8b, 0x08]), Compression::Gzip);
+        assert_eq!(
+            Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd]),
+            Compression::Zstd
+        );
+        assert_eq!(Compression::detect(b"BZh9"), Compression::Bzip2);
+        assert_eq!(Compression::detect(&[0x1f]), Compression::None);
+    }
+}
@@ -0,0 +1,64 @@
+//! diagnostics of reading gds data
+
+use crate::gds_error::GdsError;
+use std::fmt::{Display, Formatter};
+
+/// Diagnostic severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Anomaly found when reading gds data in non-strict mode
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// byte offset in gds data
+    pub offset: u64,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub(crate) fn from_error(severity: Severity, err: &GdsError) -> Self {
+        Diagnostic {
+            severity,
+            offset: err.offset().unwrap_or_default(),
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Receiver of diagnostics, like info of gds version and anomalies skipped in non-strict mode
+///
+/// embedding applications implement it to decide what is shown, see [`ReadOptions::sink`](crate::ReadOptions::sink)
+pub trait DiagnosticSink: Send + Sync {
+    fn report(&self, diagnostic: &Diagnostic);
+}
+
+/// Forward diagnostics to [`log`] facade, default sink of reading
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogSink;
+
+impl DiagnosticSink for LogSink {
+    fn report(&self, diagnostic: &Diagnostic) {
+        let level = match diagnostic.severity {
+            Severity::Info => log::Level::Info,
+            Severity::Warning => log::Level::Warn,
+            Severity::Error => log::Level::Error,
+        };
+        log::log!(level, "{}", diagnostic.message);
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "[{}] {}", severity, self.message)
+    }
+}
@@ -3,36 +3,185 @@
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 
+/// Error of reading, parsing or writing gds data
 #[derive(Debug)]
-pub struct GDSIIError {
-    err: String,
+#[non_exhaustive]
+pub enum GdsError {
+    /// io error of underlying byte stream
+    Io(std::io::Error),
+    /// byte stream ends in the middle of a record
+    Truncated { offset: u64 },
+    /// record type not defined by gds manual, record is kept as raw record in non-strict mode
+    UnknownRecord {
+        offset: u64,
+        record_type: u8,
+        data_type: u8,
+    },
+    /// record data can not be decoded, or record is not valid at its position
+    InvalidRecordData(String),
+    /// different structures have same name
+    DuplicateStructure(String),
+    /// structure names of a circular reference path, first and last is same structure
+    CircularReference(Vec<String>),
+    /// reference to a structure not found
+    UndefinedReference(String),
+    /// value can not be represented in gds file
+    ValueOutOfRange(String),
+    /// error with its position in gds data
+    Context {
+        context: ErrorContext,
+        source: Box<GdsError>,
+    },
 }
 
-impl GDSIIError {
-    pub fn new(err: &str) -> GDSIIError {
-        GDSIIError {
-            err: err.to_string(),
+/// Position in gds data where an error happened
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorContext {
+    /// byte offset of the record
+    pub offset: u64,
+    /// index of the record, starting from 0
+    pub record_index: usize,
+    /// record name, like `XY`, None for unknown record
+    pub record: Option<&'static str>,
+    /// name of enclosing structure
+    pub structure: Option<String>,
+    /// enclosing element kind and its index in structure, starting from 0
+    pub element: Option<(&'static str, usize)>,
+}
+
+impl Display for ErrorContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(name) = &self.structure {
+            write!(f, "STRUCT '{}', ", name)?;
+        }
+        if let Some((kind, idx)) = &self.element {
+            write!(f, "{} #{}, ", kind, idx)?;
         }
+        write!(
+            f,
+            "{} at offset {:#x} (record #{})",
+            self.record.unwrap_or("record"),
+            self.offset,
+            self.record_index
+        )
     }
 }
 
-impl Display for GDSIIError {
+impl GdsError {
+    /// position in gds data where error happened, if known
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            GdsError::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// byte offset in gds data where error happened, if known
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            GdsError::Context { context, .. } => Some(context.offset),
+            GdsError::Truncated { offset } | GdsError::UnknownRecord { offset, .. } => {
+                Some(*offset)
+            }
+            _ => None,
+        }
+    }
+
+    /// error without position context
+    pub fn root(&self) -> &GdsError {
+        match self {
+            GdsError::Context { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    // attach position context to error, fields already known are kept
+    pub(crate) fn in_context(self, context: ErrorContext) -> Self {
+        match self {
+            GdsError::Context {
+                context: mut cur,
+                source,
+            } => {
+                if cur.structure.is_none() {
+                    cur.structure = context.structure;
+                }
+                if cur.element.is_none() {
+                    cur.element = context.element;
+                }
+                if cur.record.is_none() {
+                    cur.record = context.record;
+                }
+                GdsError::Context {
+                    context: cur,
+                    source,
+                }
+            }
+            other => GdsError::Context {
+                context,
+                source: Box::new(other),
+            },
+        }
+    }
+}
+
+impl Display for GdsError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "[GDSIIError]: {}", self.err)
+        match self {
+            GdsError::Io(err) => write!(f, "io error: {}", err),
+            GdsError::Truncated { offset } => {
+                write!(f, "gds data truncated at offset {:#x}", offset)
+            }
+            GdsError::UnknownRecord {
+                offset,
+                record_type,
+                data_type,
+            } => write!(
+                f,
+                "unknown record type {:#04x} with data type {:#04x} at offset {:#x}",
+                record_type, data_type, offset
+            ),
+            GdsError::InvalidRecordData(msg) => write!(f, "invalid record data: {}", msg),
+            GdsError::DuplicateStructure(name) => {
+                write!(f, "struc named {} has already existed in lib", name)
+            }
+            GdsError::CircularReference(path) => {
+                write!(f, "circle refer found: {}", path.join(" -> "))
+            }
+            GdsError::UndefinedReference(name) => {
+                write!(f, "refered struc named {} not found", name)
+            }
+            GdsError::ValueOutOfRange(msg) => write!(f, "value out of range: {}", msg),
+            GdsError::Context { context, source } => write!(f, "{}: {}", context, source),
+        }
     }
 }
 
-impl Error for GDSIIError {
+impl Error for GdsError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        Some(self)
+        match self {
+            GdsError::Io(err) => Some(err),
+            GdsError::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
     }
 }
 
-#[macro_export]
-macro_rules! gds_err {
-    ( $x:expr ) => {{
-        crate::gds_error::GDSIIError::new(
-            format!("{}:{} : {}", file!(), line!(), $x).as_str(),
-        )
-    }};
+impl From<std::io::Error> for GdsError {
+    fn from(err: std::io::Error) -> Self {
+        GdsError::Io(err)
+    }
 }
+
+impl From<std::array::TryFromSliceError> for GdsError {
+    fn from(err: std::array::TryFromSliceError) -> Self {
+        GdsError::InvalidRecordData(err.to_string())
+    }
+}
+
+impl From<std::string::FromUtf8Error> for GdsError {
+    fn from(err: std::string::FromUtf8Error) -> Self {
+        GdsError::InvalidRecordData(err.to_string())
+    }
+}
+
+pub type GdsResult<T> = Result<T, GdsError>;
@@ -0,0 +1,379 @@
+//! gds lib with structure index, structures are decoded lazily
+
+use crate::gds_compress::Compression;
+use crate::gds_error::{ErrorContext, GdsError};
+use crate::gds_model::{Lib, RawAnchor, Struc};
+use crate::gds_parser;
+use crate::gds_reader::{RecordScanner, RecordSlices};
+use crate::gds_record::{self, PosRecord, Record};
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+// byte range of a structure from BGNSTR to ENDSTR
+struct StrucRange {
+    name: String,
+    range: Range<u64>,
+    // index of BGNSTR record
+    record_index: usize,
+}
+
+// where structure bytes are read from
+enum Source {
+    Mmap(Arc<Mmap>),
+    File(Mutex<File>),
+}
+
+impl Source {
+    fn bytes(&self, range: &Range<u64>) -> Result<Cow<'_, [u8]>, GdsError> {
+        match self {
+            Source::Mmap(mmap) => Ok(Cow::Borrowed(
+                &mmap[range.start as usize..range.end as usize],
+            )),
+            Source::File(file) => {
+                let mut file = file.lock().unwrap();
+                let mut bytes = vec![0_u8; (range.end - range.start) as usize];
+                file.seek(SeekFrom::Start(range.start))?;
+                file.read_exact(&mut bytes)?;
+                Ok(Cow::Owned(bytes))
+            }
+        }
+    }
+}
+
+/// Gds lib with name to byte range index of its structures
+///
+/// only record boundaries of structures are scanned when opened, a structure is decoded
+/// when first requested, together with structures it refers to
+pub struct LazyLib {
+    source: Source,
+    /// lib header records, the lib holds no structures
+    pub header: Box<Lib>,
+    // user units per database unit
+    factor: f64,
+    strucs: Vec<StrucRange>,
+    name_idx: HashMap<String, usize>,
+    decoded: Vec<OnceLock<Arc<RwLock<Struc>>>>,
+}
+
+/// index structures of gds file, structures are read from file when requested
+pub fn read_gdsii_lazy<T: AsRef<path::Path>>(gds_file: T) -> Result<LazyLib, GdsError> {
+    let mut file = File::open(gds_file)?;
+    let mut magic = Vec::with_capacity(4);
+    (&mut file).take(4).read_to_end(&mut magic)?;
+    check_uncompressed(&magic)?;
+    file.rewind()?;
+    let source = Source::File(Mutex::new(file.try_clone()?));
+    LazyLib::new(source, RecordScanner::new(BufReader::new(file)))
+}
+
+/// memory-map gds file and index its structures
+pub fn read_gdsii_mmap<T: AsRef<path::Path>>(gds_file: T) -> Result<LazyLib, GdsError> {
+    let file = File::open(gds_file)?;
+    // SAFETY: gds file is mapped read-only, modifying the file while it is mapped is not
+    // supported, like reading any file which is changed at the same time
+    let mmap = Arc::new(unsafe { Mmap::map(&file)? });
+    check_uncompressed(&mmap)?;
+    let source = Source::Mmap(mmap.clone());
+    LazyLib::new(source, RecordScanner::new(Cursor::new(&mmap[..])))
+}
+
+impl LazyLib {
+    fn new<R: Read + Seek>(
+        source: Source,
+        mut records: RecordScanner<R>,
+    ) -> Result<Self, GdsError> {
+        let mut lib = LazyLib {
+            source,
+            header: Box::new(Lib::new("")),
+            factor: 1e-3,
+            strucs: Vec::new(),
+            name_idx: HashMap::new(),
+            decoded: Vec::new(),
+        };
+        lib.index(&mut records)?;
+        lib.decoded = lib.strucs.iter().map(|_| OnceLock::new()).collect();
+        Ok(lib)
+    }
+
+    // decode lib records, and find byte range of structures without decoding them
+    fn index<R: Read + Seek>(&mut self, records: &mut RecordScanner<R>) -> Result<(), GdsError> {
+        // lazy reading is strict
+        let mut reporter = gds_parser::Reporter::new(true);
+        self.header = gds_parser::parse_lib_begin(
+            &mut std::iter::from_fn(|| next_record(records).transpose()),
+            &mut reporter,
+        )?;
+        self.factor = self.header.precision / self.header.units;
+
+        let mut order = gds_parser::LibRecordOrder::default();
+        let mut anchors = gds_parser::RawAnchors::lib();
+        loop {
+            let Some(pos_rec) = next_record(records)? else {
+                return Err(GdsError::InvalidRecordData(
+                    "no valid gds lib found, ENDLIB record missing".to_string(),
+                ));
+            };
+            let context = pos_rec.error_context();
+            order
+                .check(&pos_rec.record)
+                .map_err(|e| e.in_context(context.clone()))?;
+            if let Some(raw) = anchors.visit(&pos_rec, &mut reporter)? {
+                self.header.raw_records.push(raw);
+                continue;
+            }
+            if gds_parser::parse_lib_header(&mut self.header, &mut self.factor, &pos_rec.record)
+                .map_err(|e| e.in_context(context.clone()))?
+            {
+                continue;
+            }
+            match pos_rec.record {
+                Record::BgnStr(_) => {
+                    let struc = Self::index_struc(records, context.clone())?;
+                    if self.name_idx.contains_key(&struc.name) {
+                        return Err(GdsError::DuplicateStructure(struc.name).in_context(context));
+                    }
+                    anchors.item(RawAnchor::Struc(struc.name.clone()));
+                    self.name_idx.insert(struc.name.clone(), self.strucs.len());
+                    self.strucs.push(struc);
+                }
+                Record::EndLib => return Ok(()),
+                other => {
+                    return Err(GdsError::InvalidRecordData(format!(
+                        "unexpected {} record in library",
+                        other.name()
+                    ))
+                    .in_context(context))
+                }
+            }
+        }
+    }
+
+    // skip records of structure until ENDSTR, only STRNAME is decoded
+    fn index_struc<R: Read + Seek>(
+        records: &mut RecordScanner<R>,
+        bgn_str: ErrorContext,
+    ) -> Result<StrucRange, GdsError> {
+        let mut name = None;
+        loop {
+            let Some(header) = records.next_header()? else {
+                return Err(GdsError::InvalidRecordData(
+                    "Invalid gds structure range found".to_string(),
+                )
+                .in_context(bgn_str));
+            };
+            match &header.record_type[..] {
+                gds_record::STRNAME => {
+                    if let Record::StrName(s) = records.read_record(&header)?.record {
+                        name = Some(s);
+                    }
+                }
+                gds_record::ENDSTR => {
+                    let Some(name) = name else {
+                        return Err(GdsError::InvalidRecordData(
+                            "gds structure has no STRNAME record".to_string(),
+                        )
+                        .in_context(bgn_str));
+                    };
+                    return Ok(StrucRange {
+                        name,
+                        range: bgn_str.offset..header.offset + header.len as u64,
+                        record_index: bgn_str.record_index,
+                    });
+                }
+                gds_record::BGNSTR | gds_record::ENDLIB => {
+                    return Err(GdsError::InvalidRecordData(
+                        "Gds structure range overlap".to_string(),
+                    )
+                    .in_context(header.error_context()));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// names of all structures in file order
+    pub fn struc_names(&self) -> impl Iterator<Item = &str> {
+        self.strucs.iter().map(|s| s.name.as_str())
+    }
+
+    /// byte range of structure in gds file, from BGNSTR to ENDSTR
+    pub fn struc_range(&self, name: &str) -> Option<Range<u64>> {
+        self.name_idx
+            .get(name)
+            .map(|&idx| self.strucs[idx].range.clone())
+    }
+
+    /// get structure by name, decode it and all structures it refers to on first request
+    pub fn struc(&self, name: &str) -> Result<Arc<RwLock<Struc>>, GdsError> {
+        match self.name_idx.get(name) {
+            Some(&idx) => self.decode(idx, &mut Vec::new()),
+            None => Err(GdsError::UndefinedReference(name.to_string())),
+        }
+    }
+
+    /// decode all structures to gds lib
+    pub fn to_lib(&self) -> Result<Box<Lib>, GdsError> {
+        let strucs = (0..self.strucs.len())
+            .into_par_iter()
+            .map(|idx| self.decode(idx, &mut Vec::new()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut lib = Box::new(self.header.header_clone());
+        for struc in &strucs {
+            lib.add_struc(struc)?;
+        }
+        Ok(lib)
+    }
+
+    // decode structure and structures it refers to, `visiting` is structures being decoded
+    fn decode(
+        &self,
+        idx: usize,
+        visiting: &mut Vec<usize>,
+    ) -> Result<Arc<RwLock<Struc>>, GdsError> {
+        if let Some(struc) = self.decoded[idx].get() {
+            return Ok(struc.clone());
+        }
+        if let Some(pos) = visiting.iter().position(|&v| v == idx) {
+            let mut path = visiting[pos..]
+                .iter()
+                .map(|&v| self.strucs[v].name.clone())
+                .collect::<Vec<_>>();
+            path.push(self.strucs[idx].name.clone());
+            return Err(GdsError::CircularReference(path));
+        }
+
+        let struc_range = &self.strucs[idx];
+        let bytes = self.source.bytes(&struc_range.range)?;
+        let mut records =
+            RecordSlices::new(&bytes, struc_range.range.start, struc_range.record_index);
+        let mut pos_records = Vec::new();
+        while let Some(pos_rec) = records.next_record()? {
+            pos_records.push(pos_rec);
+        }
+        let parsed = gds_parser::parse_struc(&pos_records, self.factor, true)?;
+
+        visiting.push(idx);
+        for fake_ref in parsed.refs {
+            let Some(&ref_idx) = self.name_idx.get(&fake_ref.refed_struc_name) else {
+                return Err(GdsError::UndefinedReference(fake_ref.refed_struc_name)
+                    .in_context(fake_ref.context));
+            };
+            let ref_struc = self.decode(ref_idx, visiting)?;
+            parsed
+                .struc
+                .write()
+                .unwrap()
+                .refs
+                .push(fake_ref.create_true_ref(&ref_struc));
+        }
+        visiting.pop();
+
+        // structure may be decoded by other thread at the same time, keep the first one
+        Ok(self.decoded[idx].get_or_init(|| parsed.struc).clone())
+    }
+}
+
+// structures of compressed data can not be located by byte range
+fn check_uncompressed(magic: &[u8]) -> Result<(), GdsError> {
+    match Compression::detect(magic) {
+        Compression::None => Ok(()),
+        compression => Err(GdsError::Io(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!(
+                "{:?} compressed gds data can not be read lazily, use read_gdsii instead",
+                compression
+            ),
+        ))),
+    }
+}
+
+fn next_record<R: Read + Seek>(
+    records: &mut RecordScanner<R>,
+) -> Result<Option<PosRecord>, GdsError> {
+    match records.next_header()? {
+        Some(header) => records.read_record(&header).map(Some),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod test_gds_lazy {
+    use super::*;
+    use crate::gds_model::{Points, Polygon, Ref};
+
+    fn test_lib_file(file_name: &str) -> std::path::PathBuf {
+        let mut lib = Lib::new("test");
+        lib.version = 5;
+        let top = Arc::new(RwLock::new(Struc::new("top")));
+        let sub = Arc::new(RwLock::new(Struc::new("sub")));
+        let other = Arc::new(RwLock::new(Struc::new("other")));
+        let mut polygon = Polygon::default();
+        polygon.points.push(Points::new(0.0, 0.0));
+        polygon.points.push(Points::new(1.0, 0.0));
+        polygon.points.push(Points::new(0.0, 1.0));
+        sub.write().unwrap().polygons.push(polygon);
+        top.write().unwrap().refs.push(Ref::new(&sub));
+        top.write().unwrap().refs.push(Ref::new(&sub));
+        lib.add_struc(&top).unwrap();
+        lib.add_struc(&other).unwrap();
+
+        let file = std::env::temp_dir().join(format!("{}_{}.gds", file_name, std::process::id()));
+        std::fs::write(&file, lib.gds_bytes().unwrap()).unwrap();
+        file
+    }
+
+    fn check_lazy_lib(lazy_lib: &LazyLib) {
+        assert_eq!(lazy_lib.header.name, "test");
+        assert_eq!(lazy_lib.header.version, 5);
+        let mut names = lazy_lib.struc_names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["other", "sub", "top"]);
+        assert!(lazy_lib.decoded.iter().all(|s| s.get().is_none()));
+
+        // referenced structures are decoded with top, once and shared
+        let top = lazy_lib.struc("top").unwrap();
+        let sub = lazy_lib.decoded[lazy_lib.name_idx["sub"]].get().unwrap();
+        let top = top.read().unwrap();
+        assert_eq!(top.refs.len(), 2);
+        assert!(Arc::ptr_eq(&top.refs[0].refed_struc, sub));
+        assert!(Arc::ptr_eq(&top.refs[1].refed_struc, sub));
+        assert!(Arc::ptr_eq(&lazy_lib.struc("sub").unwrap(), sub));
+        assert_eq!(sub.read().unwrap().polygons.len(), 1);
+        assert!(lazy_lib.decoded[lazy_lib.name_idx["other"]].get().is_none());
+        assert!(matches!(
+            lazy_lib.struc("missing"),
+            Err(GdsError::UndefinedReference(_))
+        ));
+
+        let read_lib = lazy_lib.to_lib().unwrap();
+        assert_eq!(read_lib.all_strucs().len(), 3);
+        assert_eq!(read_lib.version, 5);
+    }
+
+    #[test]
+    fn test_read_gdsii_mmap() {
+        let file = test_lib_file("gdsdk_mmap");
+        let lazy_lib = read_gdsii_mmap(&file).unwrap();
+        std::fs::remove_file(&file).unwrap();
+        check_lazy_lib(&lazy_lib);
+    }
+
+    #[test]
+    fn test_read_gdsii_lazy() {
+        let file = test_lib_file("gdsdk_lazy");
+        let lazy_lib = read_gdsii_lazy(&file).unwrap();
+        let range = lazy_lib.struc_range("sub").unwrap();
+        let bytes = std::fs::read(&file).unwrap();
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(bytes[range.start as usize + 2..][..2], *gds_record::BGNSTR);
+        assert_eq!(bytes[range.end as usize - 2..][..2], *gds_record::ENDSTR);
+        check_lazy_lib(&lazy_lib);
+    }
+}
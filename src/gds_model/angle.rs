@@ -0,0 +1,60 @@
+/// Rotation angle, counterclockwise
+///
+/// kept in degrees as in ANGLE record, so angle read from gds data is written back unchanged
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Angle(f64);
+
+impl Angle {
+    pub fn from_degrees(degrees: f64) -> Self {
+        Angle(degrees)
+    }
+
+    pub fn from_radians(radians: f64) -> Self {
+        Angle(radians.to_degrees())
+    }
+
+    pub fn degrees(&self) -> f64 {
+        self.0
+    }
+
+    pub fn radians(&self) -> f64 {
+        self.0.to_radians()
+    }
+}
+
+#[cfg(test)]
+mod test_angle {
+    use crate::gds_model::test_gds_model::*;
+    use crate::gds_model::*;
+    use std::sync::{Arc, RwLock};
+
+    #[test]
+    fn test_rotation_round_trip() {
+        let mut lib = Lib::new("test");
+        let top = Arc::new(RwLock::new(Struc::new("top")));
+        let cell = Arc::new(RwLock::new(Struc::new("cell")));
+        // every 0.01 degree, ANGLE bytes are same after read and write
+        let mut degrees = (0..36000).map(|d| d as f64 / 100.0).collect::<Vec<_>>();
+        degrees.extend([33.3, -45.0, 720.0]);
+        for &degree in &degrees {
+            let mut sref = Ref::new(&cell);
+            sref.angle = Angle::from_degrees(degree);
+            let text = Text {
+                text: "label".to_string(),
+                rotation: Angle::from_degrees(degree),
+                ..Default::default()
+            };
+            top.write().unwrap().refs.push(sref);
+            top.write().unwrap().label.push(text);
+        }
+        lib.add_struc(&top).unwrap();
+        let (_, read_lib) = round_trip(&lib);
+
+        let read_top = struc_of(&read_lib, "top");
+        let read_top = read_top.read().unwrap();
+        for (i, degree) in degrees.iter().enumerate() {
+            assert_eq!(read_top.refs[i].angle.degrees(), *degree);
+            assert_eq!(read_top.label[i].rotation.degrees(), *degree);
+        }
+    }
+}
@@ -1,5 +1,5 @@
-use chrono::{DateTime, Datelike, Timelike, Utc};
-use std::{error::Error, time::SystemTime};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Timelike, Utc};
+use std::time::SystemTime;
 
 use super::*;
 
@@ -67,11 +67,54 @@ impl Date {
         }
     }
 
-    pub fn from_i16_array(date: &[i16]) -> Result<Date, Box<dyn Error>> {
+    /// date with both modification and access time set to `time`
+    pub fn from_naive(time: &NaiveDateTime) -> Result<Date, GdsError> {
+        let [year, month, day, hour, minute, second] = naive_to_i16_array(time)?;
+        Ok(Date {
+            mod_year: year,
+            mod_month: month,
+            mod_day: day,
+            mod_hour: hour,
+            mod_minute: minute,
+            mod_second: second,
+            acc_year: year,
+            acc_month: month,
+            acc_day: day,
+            acc_hour: hour,
+            acc_minute: minute,
+            acc_second: second,
+        })
+    }
+
+    /// last modification time, error if it is not a valid date and time
+    pub fn modification(&self) -> Result<NaiveDateTime, GdsError> {
+        i16_array_to_naive([
+            self.mod_year,
+            self.mod_month,
+            self.mod_day,
+            self.mod_hour,
+            self.mod_minute,
+            self.mod_second,
+        ])
+    }
+
+    /// last access time, error if it is not a valid date and time
+    pub fn access(&self) -> Result<NaiveDateTime, GdsError> {
+        i16_array_to_naive([
+            self.acc_year,
+            self.acc_month,
+            self.acc_day,
+            self.acc_hour,
+            self.acc_minute,
+            self.acc_second,
+        ])
+    }
+
+    pub fn from_i16_array(date: &[i16]) -> Result<Date, GdsError> {
         if date.len() < 12 {
-            return Err(Box::new(gds_err!(
-                "Can't create gds Date for data length less than 12"
-            )));
+            return Err(GdsError::InvalidRecordData(
+                "Can't create gds Date for data length less than 12".to_string(),
+            ));
         }
         let mut it = date.iter();
         Ok(Date {
@@ -91,8 +134,53 @@ impl Date {
     }
 }
 
+impl TryFrom<&NaiveDateTime> for Date {
+    type Error = GdsError;
+    fn try_from(value: &NaiveDateTime) -> Result<Self, Self::Error> {
+        Date::from_naive(value)
+    }
+}
+
+impl TryFrom<&Date> for NaiveDateTime {
+    type Error = GdsError;
+    fn try_from(value: &Date) -> Result<Self, Self::Error> {
+        value.modification()
+    }
+}
+
+// year, month, day, hour, minute, second
+fn i16_array_to_naive(date: [i16; 6]) -> Result<NaiveDateTime, GdsError> {
+    let [year, month, day, hour, minute, second] = date;
+    let unsigned = |v: i16| u32::try_from(v).ok();
+    unsigned(month)
+        .zip(unsigned(day))
+        .and_then(|(month, day)| NaiveDate::from_ymd_opt(year as i32, month, day))
+        .zip(unsigned(hour).zip(unsigned(minute)).zip(unsigned(second)))
+        .and_then(|(date, ((hour, minute), second))| date.and_hms_opt(hour, minute, second))
+        .ok_or_else(|| {
+            GdsError::InvalidRecordData(format!("not valid gds date and time: {:?}", date))
+        })
+}
+
+fn naive_to_i16_array(time: &NaiveDateTime) -> Result<[i16; 6], GdsError> {
+    let year = i16::try_from(time.year())
+        .ok()
+        .filter(|year| *year >= 0)
+        .ok_or_else(|| {
+            GdsError::ValueOutOfRange(format!("Gds date year can not be {}", time.year()))
+        })?;
+    Ok([
+        year,
+        time.month() as i16,
+        time.day() as i16,
+        time.hour() as i16,
+        time.minute() as i16,
+        time.second() as i16,
+    ])
+}
+
 impl GdsObject for Date {
-    fn to_gds(&self, _: f64) -> Result<Vec<u8>, Box<dyn Error>> {
+    fn to_gds(&self, _: f64) -> Result<Vec<u8>, GdsError> {
         let mut date_data = Vec::<u8>::new();
         date_data.extend(self.mod_year.to_be_bytes());
         date_data.extend(self.mod_month.to_be_bytes());
@@ -109,3 +197,37 @@ impl GdsObject for Date {
         Ok(date_data)
     }
 }
+
+#[cfg(test)]
+mod test_date {
+    use super::*;
+
+    #[test]
+    fn test_date_naive_conversion() {
+        let time = chrono::NaiveDate::from_ymd_opt(2024, 2, 29)
+            .unwrap()
+            .and_hms_opt(23, 59, 58)
+            .unwrap();
+        let date = Date::try_from(&time).unwrap();
+        assert_eq!((date.mod_year, date.mod_month, date.mod_day), (2024, 2, 29));
+        assert_eq!(
+            (date.acc_hour, date.acc_minute, date.acc_second),
+            (23, 59, 58)
+        );
+        assert_eq!(chrono::NaiveDateTime::try_from(&date).unwrap(), time);
+        assert_eq!(date.access().unwrap(), time);
+
+        // invalid dates are rejected
+        let mut date = date;
+        date.mod_month = 13;
+        assert!(date.modification().is_err());
+        date.acc_day = -1;
+        assert!(date.access().is_err());
+        assert!(Date::new().modification().is_err());
+        let far = chrono::NaiveDate::from_ymd_opt(40000, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        assert!(Date::from_naive(&far).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use super::*;
+use crate::gds_record;
+
+/// ELFLAGS of element, bit 15 is template data and bit 14 is external data
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ElFlags(pub u16);
+
+impl ElFlags {
+    const TEMPLATE: u16 = 0x0001;
+    const EXTERNAL: u16 = 0x0002;
+
+    pub fn template(&self) -> bool {
+        self.0 & Self::TEMPLATE != 0
+    }
+
+    pub fn external(&self) -> bool {
+        self.0 & Self::EXTERNAL != 0
+    }
+
+    pub fn set_template(&mut self, template: bool) {
+        self.set(Self::TEMPLATE, template);
+    }
+
+    pub fn set_external(&mut self, external: bool) {
+        self.set(Self::EXTERNAL, external);
+    }
+
+    fn set(&mut self, bit: u16, value: bool) {
+        if value {
+            self.0 |= bit;
+        } else {
+            self.0 &= !bit;
+        }
+    }
+}
+
+impl GdsObject for ElFlags {
+    fn to_gds(&self, _: f64) -> Result<Vec<u8>, GdsError> {
+        let mut data = Vec::<u8>::new();
+        data.extend(6_i16.to_be_bytes());
+        data.extend(gds_record::ELFLAGS);
+        data.extend(self.0.to_be_bytes());
+        Ok(data)
+    }
+}
+
+/// PLEX of element, plex number is in the rightmost 24 bits, bit 7 marks head of plex
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Plex(pub i32);
+
+impl Plex {
+    const HEAD: i32 = 0x0100_0000;
+    const NUMBER: i32 = 0x00ff_ffff;
+
+    pub fn new(number: i32, head: bool) -> Self {
+        let mut plex = Plex(number & Self::NUMBER);
+        if head {
+            plex.0 |= Self::HEAD;
+        }
+        plex
+    }
+
+    pub fn number(&self) -> i32 {
+        self.0 & Self::NUMBER
+    }
+
+    pub fn is_head(&self) -> bool {
+        self.0 & Self::HEAD != 0
+    }
+}
+
+impl GdsObject for Plex {
+    fn to_gds(&self, _: f64) -> Result<Vec<u8>, GdsError> {
+        if self.0 < 0 {
+            return Err(GdsError::ValueOutOfRange(format!(
+                "Gds plex can not be negative:{:#?}",
+                &self
+            )));
+        }
+        let mut data = Vec::<u8>::new();
+        data.extend(8_i16.to_be_bytes());
+        data.extend(gds_record::PLEX);
+        data.extend(self.0.to_be_bytes());
+        Ok(data)
+    }
+}
+
+// optional ELFLAGS and PLEX records following element header record
+pub(crate) fn element_flags_to_gds(
+    elflags: &Option<ElFlags>,
+    plex: &Option<Plex>,
+) -> Result<Vec<u8>, GdsError> {
+    let mut data = Vec::<u8>::new();
+    if let Some(elflags) = elflags {
+        data.extend(elflags.to_gds(1.0)?);
+    }
+    if let Some(plex) = plex {
+        data.extend(plex.to_gds(1.0)?);
+    }
+    Ok(data)
+}
+
+/// Borrowed element of structure
+#[derive(Debug, Clone, Copy)]
+pub enum Element<'a> {
+    Polygon(&'a Polygon),
+    Path(&'a Path),
+    Ref(&'a Ref),
+    Text(&'a Text),
+    Node(&'a Node),
+    Box(&'a GdsBox),
+}
+
+/// Kind of element, like `Element` without the element
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ElementKind {
+    Polygon,
+    Path,
+    Ref,
+    Text,
+    Node,
+    Box,
+}
+
+impl Element<'_> {
+    pub fn kind(&self) -> ElementKind {
+        match self {
+            Element::Polygon(_) => ElementKind::Polygon,
+            Element::Path(_) => ElementKind::Path,
+            Element::Ref(_) => ElementKind::Ref,
+            Element::Text(_) => ElementKind::Text,
+            Element::Node(_) => ElementKind::Node,
+            Element::Box(_) => ElementKind::Box,
+        }
+    }
+
+    pub fn elflags(&self) -> Option<ElFlags> {
+        match self {
+            Element::Polygon(e) => e.elflags,
+            Element::Path(e) => e.elflags,
+            Element::Ref(e) => e.elflags,
+            Element::Text(e) => e.elflags,
+            Element::Node(e) => e.elflags,
+            Element::Box(e) => e.elflags,
+        }
+    }
+
+    pub fn plex(&self) -> Option<Plex> {
+        match self {
+            Element::Polygon(e) => e.plex,
+            Element::Path(e) => e.plex,
+            Element::Ref(e) => e.plex,
+            Element::Text(e) => e.plex,
+            Element::Node(e) => e.plex,
+            Element::Box(e) => e.plex,
+        }
+    }
+
+    // check records of element are supported by gds stream release
+    pub(crate) fn check_release(&self, release: i16) -> Result<(), GdsError> {
+        match self {
+            Element::Path(path) => path.check_release(release)?,
+            Element::Node(_) => check_record_release("NODE", 3, release)?,
+            Element::Box(_) => check_record_release("BOX", 4, release)?,
+            _ => (),
+        }
+        if self.elflags().is_some() {
+            check_record_release("ELFLAGS", 3, release)?;
+        }
+        if self.plex().is_some() {
+            check_record_release("PLEX", 5, release)?;
+        }
+        Ok(())
+    }
+}
+
+impl Struc {
+    /// all elements of structure, in writing order
+    pub fn elements(&self) -> impl Iterator<Item = Element<'_>> {
+        self.polygons
+            .iter()
+            .map(Element::Polygon)
+            .chain(self.paths.iter().map(Element::Path))
+            .chain(self.refs.iter().map(Element::Ref))
+            .chain(self.label.iter().map(Element::Text))
+            .chain(self.nodes.iter().map(Element::Node))
+            .chain(self.boxes.iter().map(Element::Box))
+    }
+
+    /// elements belonging to plex `number`
+    pub fn plex_elements(&self, number: i32) -> Vec<Element<'_>> {
+        self.elements()
+            .filter(|e| e.plex().is_some_and(|p| p.number() == number))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test_element {
+    use super::*;
+    use crate::gds_model::test_gds_model::*;
+
+    #[test]
+    fn test_flag_bits() {
+        let mut elflags = ElFlags::default();
+        elflags.set_external(true);
+        assert!(elflags.external());
+        assert!(!elflags.template());
+        elflags.set_template(true);
+        elflags.set_external(false);
+        assert_eq!(elflags, ElFlags(0x0001));
+
+        let plex = Plex::new(42, true);
+        assert_eq!(plex.0, 0x0100_002a);
+        assert_eq!(plex.number(), 42);
+        assert!(plex.is_head());
+        assert!(!Plex(42).is_head());
+        assert!(Plex(-1).to_gds(1.0).is_err());
+    }
+
+    #[test]
+    fn test_elflags_plex_round_trip() {
+        let polygon = Polygon {
+            layer: 1,
+            points: vec![
+                Points::new(0.0, 0.0),
+                Points::new(1.0, 0.0),
+                Points::new(1.0, 1.0),
+            ],
+            elflags: Some(ElFlags(0x0002)),
+            plex: Some(Plex::new(42, true)),
+            ..Default::default()
+        };
+        let path = Path {
+            layer: 2,
+            points: vec![Points::new(0.0, 0.0), Points::new(2.0, 0.0)],
+            plex: Some(Plex::new(42, false)),
+            ..Default::default()
+        };
+        let text = Text {
+            text: "label".to_string(),
+            plex: Some(Plex::new(7, true)),
+            ..Default::default()
+        };
+        let (lib, _) = cell_lib(|s| {
+            s.polygons.push(polygon);
+            s.paths.push(path);
+            s.label.push(text);
+        });
+        let (_, read_lib) = round_trip(&lib);
+
+        let read_struc = struc_of(&read_lib, "cell");
+        let read_struc = read_struc.read().unwrap();
+        let polygon = &read_struc.polygons[0];
+        assert!(polygon.elflags.unwrap().external());
+        assert!(polygon.plex.unwrap().is_head());
+        assert_eq!(read_struc.paths[0].elflags, None);
+
+        let plex = read_struc.plex_elements(42);
+        assert_eq!(plex.len(), 2);
+        assert!(matches!(plex[0], Element::Polygon(_)));
+        assert!(matches!(plex[1], Element::Path(p) if !p.plex.unwrap().is_head()));
+        assert_eq!(read_struc.plex_elements(7).len(), 1);
+        assert!(read_struc.plex_elements(1).is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+use super::*;
+use crate::gds_record;
+
+/// Gds Box, rectangle used as documentation or placeholder, not geometry
+#[derive(Default, Debug)]
+pub struct GdsBox {
+    pub layer: i16,
+    pub boxtype: i16,
+    /// 4 corner points, no need to be close
+    pub points: Vec<Points>,
+    pub property: Property,
+    /// ELFLAGS record of element
+    pub elflags: Option<ElFlags>,
+    /// PLEX record of element
+    pub plex: Option<Plex>,
+    /// records not decoded, written back at their position in element
+    pub raw_records: Vec<RawRecord>,
+}
+
+/// treat box as geometry, boxtype becomes polygon datatype
+impl From<GdsBox> for Polygon {
+    fn from(value: GdsBox) -> Self {
+        Polygon {
+            layer: value.layer,
+            datatype: value.boxtype,
+            points: value.points,
+            property: value.property,
+            elflags: value.elflags,
+            plex: value.plex,
+            raw_records: value
+                .raw_records
+                .into_iter()
+                .map(|mut raw| {
+                    // BOX and BOXTYPE anchors become BOUNDARY and DATATYPE of polygon
+                    if let RawAnchor::Record { record_type, .. } = &mut raw.anchor {
+                        if *record_type == gds_record::BOX[0] {
+                            *record_type = gds_record::BOUNDARY[0];
+                        } else if *record_type == gds_record::BOXTYPE[0] {
+                            *record_type = gds_record::DATATYPE[0];
+                        }
+                    }
+                    raw
+                })
+                .collect(),
+        }
+    }
+}
+
+impl GdsObject for GdsBox {
+    fn to_gds(&self, scaling: f64) -> Result<Vec<u8>, GdsError> {
+        let mut data = Vec::<u8>::new();
+
+        // box
+        data.extend(4_i16.to_be_bytes());
+        data.extend(gds_record::BOX);
+
+        // elflags and plex
+        data.extend(element_flags_to_gds(&self.elflags, &self.plex)?);
+
+        // layer
+        data.extend(6_i16.to_be_bytes());
+        data.extend(gds_record::LAYER);
+        data.extend(self.layer.to_be_bytes());
+
+        // boxtype
+        data.extend(6_i16.to_be_bytes());
+        data.extend(gds_record::BOXTYPE);
+        data.extend(self.boxtype.to_be_bytes());
+
+        // points
+        if self.points.len() != 4 {
+            return Err(GdsError::ValueOutOfRange(format!(
+                "Gds box should have 4 points, found {}",
+                self.points.len()
+            )));
+        }
+        // gds box points front is same as end
+        data.extend((4_i16 + 8 * 5).to_be_bytes());
+        data.extend(gds_record::XY);
+        self.points
+            .iter()
+            .chain(self.points.first())
+            .for_each(|point| {
+                let x = point.x * scaling;
+                let y = point.y * scaling;
+                data.extend((f64::round(x) as i32).to_be_bytes());
+                data.extend((f64::round(y) as i32).to_be_bytes());
+            });
+
+        // properties
+        data.extend(self.property.to_gds(scaling)?);
+
+        // endel
+        data.extend(4_i16.to_be_bytes());
+        data.extend(gds_record::ENDEL);
+
+        with_raw_records(data, &self.raw_records)
+    }
+}
+
+#[cfg(test)]
+mod test_gds_box {
+    use super::*;
+    use crate::gds_model::test_gds_model::*;
+
+    #[test]
+    fn test_box_round_trip() {
+        let gds_box = GdsBox {
+            layer: 2,
+            boxtype: 5,
+            points: vec![
+                Points::new(0.0, 0.0),
+                Points::new(2.0, 0.0),
+                Points::new(2.0, 1.0),
+                Points::new(0.0, 1.0),
+            ],
+            ..Default::default()
+        };
+        let (lib, _) = cell_lib(|s| s.boxes.push(gds_box));
+        let (_, read_lib) = round_trip(&lib);
+
+        let read_struc = struc_of(&read_lib, "cell");
+        let mut read_struc = read_struc.write().unwrap();
+        assert!(read_struc.polygons.is_empty());
+        assert_eq!(read_struc.boxes.len(), 1);
+        assert_eq!(read_struc.boxes[0].boxtype, 5);
+        assert_eq!(read_struc.boxes[0].points.len(), 4);
+
+        let polygon = Polygon::from(read_struc.boxes.pop().unwrap());
+        assert_eq!(polygon.layer, 2);
+        assert_eq!(polygon.datatype, 5);
+        assert_eq!(polygon.points.len(), 4);
+    }
+
+    #[test]
+    fn test_box_raw_record_to_polygon() {
+        let gds_box = GdsBox {
+            layer: 2,
+            boxtype: 5,
+            points: vec![
+                Points::new(0.0, 0.0),
+                Points::new(2.0, 0.0),
+                Points::new(2.0, 1.0),
+                Points::new(0.0, 1.0),
+            ],
+            raw_records: vec![RawRecord {
+                anchor: RawAnchor::Record {
+                    record_type: gds_record::BOXTYPE[0],
+                    occurrence: 0,
+                },
+                record_type: 0x43,
+                data_type: 0x06,
+                bytes: b"uc".to_vec(),
+            }],
+            ..Default::default()
+        };
+        let polygon = Polygon::from(gds_box);
+        assert_eq!(
+            polygon.raw_records[0].anchor,
+            RawAnchor::Record {
+                record_type: gds_record::DATATYPE[0],
+                occurrence: 0,
+            }
+        );
+
+        // raw record follows DATATYPE of polygon
+        let (lib, _) = cell_lib(|s| s.polygons.push(polygon));
+        let (bytes, _) = round_trip(&lib);
+        let datatype_raw: &[u8] = &[
+            0x00, 0x06, 0x0e, 0x02, 0x00, 0x05, 0x00, 0x06, 0x43, 0x06, b'u', b'c',
+        ];
+        assert!(bytes.windows(12).any(|w| w == datatype_raw));
+    }
+}
@@ -1,16 +1,19 @@
-use petgraph::algo::is_cyclic_directed;
+use petgraph::algo::{astar, toposort};
 use petgraph::graph::{EdgeIndex, NodeIndex};
 use petgraph::stable_graph::StableDiGraph;
 use petgraph::Direction;
 
 use multi_index_map::MultiIndexMap;
 
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::io::Write;
 use std::sync::{Arc, RwLock};
 
 use super::*;
-use crate::{gds_record, gds_writer};
+use crate::gds_compress::Encoder;
+use crate::{gds_record, gds_writer, StrucOrder, Timestamps, WriteOptions};
 
 #[derive(Debug)]
 struct HashStrucAddr(Arc<RwLock<Struc>>);
@@ -75,9 +78,42 @@ pub struct Lib {
     /// default is 1e-9
     pub precision: f64,
     pub date: Date,
+    /// gds stream version in HEADER record, read from file and written back
+    ///
+    /// 0 for release before 3.0, 3, 4 and 5 for releases 3.0 to 5.0, 600 for release 6.0
+    /// and 7 for release 7.0, other versions are rejected on reading and writing,
+    /// features not supported by this version are rejected on writing
+    ///
+    /// default is 600
+    pub version: i16,
+    /// name of sticks rules file in SRFNAME record
+    pub srfname: Option<String>,
+    /// names of reference libraries in REFLIBS record
+    pub reflibs: Vec<String>,
+    /// names of 4 text font definition files in FONTS record
+    pub fonts: Option<[String; 4]>,
+    /// name of attribute definition file in ATTRTABLE record
+    pub attrtable: Option<String>,
+    /// count of structure copies kept in GENERATIONS record, 2 to 99
+    pub generations: Option<i16>,
+    /// FORMAT record with MASK records of filtered format
+    pub format: Option<LibFormat>,
+    /// records not decoded, written back at their position in lib
+    pub raw_records: Vec<RawRecord>,
     pub(self) graph: StableDiGraph<Arc<RwLock<Struc>>, ()>,
     // strucs_nodeidx_map: HashMap<HashStrucAddr, NodeIndex<u32>>,
     uniq_struct: MultiIndexUniqStructMap,
+    // insert order of next added struc
+    next_order: usize,
+}
+
+/// Format type of lib, 0 is archive and 1 is filtered format
+///
+/// masks of filtered format list layers and datatypes kept in it, like `1-3 5 ; 0`
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct LibFormat {
+    pub format_type: i16,
+    pub masks: Vec<String>,
 }
 
 #[derive(MultiIndexMap, Debug)]
@@ -89,6 +125,7 @@ pub(crate) struct UniqStruct {
     pub(crate) struct_name: String,
     #[multi_index(hashed_unique)]
     struct_address: HashStrucAddr,
+    insert_order: usize,
 }
 
 impl Lib {
@@ -98,8 +135,36 @@ impl Lib {
             units: 1e-6,
             precision: 1e-9,
             date: Date::now(),
+            version: GDS_VERSIOIN,
+            srfname: None,
+            reflibs: Vec::new(),
+            fonts: None,
+            attrtable: None,
+            generations: None,
+            format: None,
+            raw_records: Vec::new(),
             graph: StableDiGraph::<Arc<RwLock<Struc>>, ()>::new(),
             uniq_struct: MultiIndexUniqStructMap::default(),
+            next_order: 0,
+        }
+    }
+
+    // copy of lib header records, without structures
+    pub(crate) fn header_clone(&self) -> Self {
+        Lib {
+            name: self.name.clone(),
+            units: self.units,
+            precision: self.precision,
+            date: self.date.clone(),
+            version: self.version,
+            srfname: self.srfname.clone(),
+            reflibs: self.reflibs.clone(),
+            fonts: self.fonts.clone(),
+            attrtable: self.attrtable.clone(),
+            generations: self.generations,
+            format: self.format.clone(),
+            raw_records: self.raw_records.clone(),
+            ..Lib::new("")
         }
     }
 
@@ -109,18 +174,17 @@ impl Lib {
     /// struc_a has a ref which refer to struc_b
     ///
     /// lib.add_struc(struc_a) will also add struc_b
-    pub fn add_struc(&mut self, struc: &Arc<RwLock<Struc>>) -> Result<(), Box<dyn Error+Send+Sync>> {
+    pub fn add_struc(&mut self, struc: &Arc<RwLock<Struc>>) -> Result<(), GdsError> {
         // different struct object may have same name, gds formt forbidd same name struct in lib
-        if self.diff_struct_has_same_name(&struc) {
-            return Err(Box::new(gds_err!(&format!(
-                "struc named {} has already existed in lib",
-                struc.read().unwrap().name
-            ))));
+        if self.diff_struct_has_same_name(struc) {
+            return Err(GdsError::DuplicateStructure(
+                struc.read().unwrap().name.clone(),
+            ));
         }
         // check if struc had been added
         match self
             .uniq_struct
-            .get_by_struct_address(&HashStrucAddr::new(&struc))
+            .get_by_struct_address(&HashStrucAddr::new(struc))
         {
             Some(_) => {
                 // if struct had been added before, just recursively add refered strucs
@@ -132,12 +196,7 @@ impl Lib {
             }
             None => {
                 // add struc to graph
-                let nodeidx = self.graph.add_node(struc.clone());
-                self.uniq_struct.insert(UniqStruct {
-                    graph_idx: nodeidx,
-                    struct_name: struc.read().unwrap().name.clone(),
-                    struct_address: HashStrucAddr::new(&struc),
-                });
+                let nodeidx = self.insert_struc(struc);
                 // recursly add refered strucs
                 for r in &struc.read().unwrap().refs {
                     if let Err(e) = self.add_referd_struc(struc.clone(), r.refed_struc.clone()) {
@@ -151,19 +210,31 @@ impl Lib {
         }
     }
 
+    fn insert_struc(&mut self, struc: &Arc<RwLock<Struc>>) -> NodeIndex<u32> {
+        let nodeidx = self.graph.add_node(struc.clone());
+        self.uniq_struct.insert(UniqStruct {
+            graph_idx: nodeidx,
+            struct_name: struc.read().unwrap().name.clone(),
+            struct_address: HashStrucAddr::new(struc),
+            insert_order: self.next_order,
+        });
+        self.next_order += 1;
+        nodeidx
+    }
+
     fn add_referd_struc(
         &mut self,
         from_struct: Arc<RwLock<Struc>>,
         struc: Arc<RwLock<Struc>>,
-    ) -> Result<(), Box<dyn Error+Send+Sync>> {
-        if is_cyclic_directed(&self.graph) {
-            return Err(Box::new(gds_err!(&"circle refer found")));
+    ) -> Result<(), GdsError> {
+        let circle_path = self.circle_refer_path();
+        if !circle_path.is_empty() {
+            return Err(GdsError::CircularReference(circle_path));
         }
         if self.diff_struct_has_same_name(&struc) {
-            return Err(Box::new(gds_err!(&format!(
-                "struc named {} has already existed in lib",
-                struc.read().unwrap().name
-            ))));
+            return Err(GdsError::DuplicateStructure(
+                struc.read().unwrap().name.clone(),
+            ));
         }
         let from_nodeidx = self
             .uniq_struct
@@ -189,12 +260,7 @@ impl Lib {
                 };
             }
         } else {
-            let nodeidx = self.graph.add_node(struc.clone());
-            self.uniq_struct.insert(UniqStruct {
-                graph_idx: nodeidx,
-                struct_name: struc.read().unwrap().name.clone(),
-                struct_address: HashStrucAddr::new(&struc),
-            });
+            let nodeidx = self.insert_struc(&struc);
             self.graph.add_edge(from_nodeidx, nodeidx, ());
 
             for r in &struc.read().unwrap().refs {
@@ -217,7 +283,7 @@ impl Lib {
     pub fn remove_struc(&mut self, struc: &Arc<RwLock<Struc>>) {
         if let Some(uniq_struc) = self
             .uniq_struct
-            .remove_by_struct_address(&HashStrucAddr::new(&struc))
+            .remove_by_struct_address(&HashStrucAddr::new(struc))
         {
             self.graph.remove_node(uniq_struc.graph_idx);
         }
@@ -228,11 +294,28 @@ impl Lib {
             .uniq_struct
             .get_by_struct_name(&struc.read().unwrap().name)
         {
-            if same_name_struc.struct_address != HashStrucAddr::new(&struc) {
+            if same_name_struc.struct_address != HashStrucAddr::new(struc) {
                 return true;
             }
         }
-        return false;
+        false
+    }
+
+    // struc names of a circle refer path in graph, empty if no circle found
+    fn circle_refer_path(&self) -> Vec<String> {
+        let node = match toposort(&self.graph, None) {
+            Ok(_) => return Vec::new(),
+            Err(cycle) => cycle.node_id(),
+        };
+        for next in self.graph.neighbors_directed(node, Direction::Outgoing) {
+            if let Some((_, path)) = astar(&self.graph, next, |n| n == node, |_| 1, |_| 0) {
+                return std::iter::once(node)
+                    .chain(path)
+                    .map(|n| self.graph[n].read().unwrap().name.clone())
+                    .collect();
+            }
+        }
+        Vec::new()
     }
 
     /// Get Strucs not refered by any Ref
@@ -240,11 +323,11 @@ impl Lib {
         let mut top_struc = Vec::<Arc<RwLock<Struc>>>::new();
 
         for node in self.graph.node_indices() {
-            if !self
+            if self
                 .graph
                 .neighbors_directed(node, Direction::Incoming)
                 .next()
-                .is_some()
+                .is_none()
             {
                 top_struc.push(
                     self.uniq_struct
@@ -260,24 +343,217 @@ impl Lib {
         top_struc
     }
 
-    /// Get all Strucs
+    /// Get all Strucs, in order of adding to lib
     pub fn all_strucs(&self) -> Vec<Arc<RwLock<Struc>>> {
-        self.uniq_struct
-            .iter()
-            .map(|c| c.1.struct_address.0.clone())
-            .collect::<Vec<_>>()
+        self.ordered_strucs(StrucOrder::Insertion)
+    }
+
+    /// Get all Strucs in given order
+    pub fn ordered_strucs(&self, order: StrucOrder) -> Vec<Arc<RwLock<Struc>>> {
+        let mut uniq_strucs = self.uniq_struct.iter().map(|c| c.1).collect::<Vec<_>>();
+        uniq_strucs.sort_by_key(|c| c.insert_order);
+        match order {
+            StrucOrder::Insertion => {}
+            StrucOrder::Alphabetical => {
+                uniq_strucs.sort_by(|a, b| a.struct_name.cmp(&b.struct_name))
+            }
+            StrucOrder::BottomUp => {
+                // depth first from each struc, refered strucs are put before referring one
+                let mut visited = HashSet::new();
+                let mut ordered = Vec::with_capacity(uniq_strucs.len());
+                for uniq_struc in &uniq_strucs {
+                    self.visit_refered(uniq_struc, &mut visited, &mut ordered);
+                }
+                uniq_strucs = ordered;
+            }
+        }
+        uniq_strucs
+            .into_iter()
+            .map(|c| c.struct_address.0.clone())
+            .collect()
+    }
+
+    fn visit_refered<'a>(
+        &'a self,
+        uniq_struc: &'a UniqStruct,
+        visited: &mut HashSet<NodeIndex<u32>>,
+        ordered: &mut Vec<&'a UniqStruct>,
+    ) {
+        if !visited.insert(uniq_struc.graph_idx) {
+            return;
+        }
+        let mut refered = self
+            .graph
+            .neighbors_directed(uniq_struc.graph_idx, Direction::Outgoing)
+            .filter_map(|node| self.uniq_struct.get_by_graph_idx(&node))
+            .collect::<Vec<_>>();
+        refered.sort_by_key(|c| c.insert_order);
+        for refered_struc in refered {
+            self.visit_refered(refered_struc, visited, ordered);
+        }
+        ordered.push(uniq_struc);
     }
 
     /// Dump Lib and recurse dump Lib's Strucs to gds file bytes
-    pub fn gds_bytes(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+    pub fn gds_bytes(&self) -> Result<Vec<u8>, GdsError> {
         self.to_gds(0.0)
     }
+
+    // check lib version and features used by lib and strucs are supported by it
+    fn check_version(&self) -> Result<(), GdsError> {
+        let release = gds_release(self.version)?;
+        self.check_header_release(release)?;
+        for (_idx, uniq_struc) in self.uniq_struct.iter() {
+            uniq_struc
+                .struct_address
+                .0
+                .read()
+                .unwrap()
+                .check_release(release)?;
+        }
+        Ok(())
+    }
+}
+
+impl Lib {
+    // check optional lib header records are supported by gds stream release
+    pub(crate) fn check_header_release(&self, release: i16) -> Result<(), GdsError> {
+        if self.srfname.is_some() {
+            check_record_release("SRFNAME", 3, release)?;
+        }
+        if !self.reflibs.is_empty() {
+            check_record_release("REFLIBS", 3, release)?;
+        }
+        if self.fonts.is_some() {
+            check_record_release("FONTS", 3, release)?;
+        }
+        if self.attrtable.is_some() {
+            check_record_release("ATTRTABLE", 3, release)?;
+        }
+        if self.generations.is_some() {
+            check_record_release("GENERATIONS", 3, release)?;
+        }
+        if self.format.is_some() {
+            check_record_release("FORMAT", 4, release)?;
+        }
+        Ok(())
+    }
 }
 
 const GDS_VERSIOIN: i16 = 600;
 
-impl GdsObject for Lib {
-    fn to_gds(&self, _: f64) -> Result<Vec<u8>, Box<dyn Error>> {
+// major gds stream release of HEADER version, 600 is release 6
+pub(crate) fn gds_release(version: i16) -> Result<i16, GdsError> {
+    match version {
+        0 | 3 | 4 | 5 | 7 => Ok(version),
+        600 => Ok(6),
+        _ => Err(GdsError::ValueOutOfRange(format!(
+            "not valid gds version: {}",
+            version
+        ))),
+    }
+}
+
+// check record is supported by gds stream release
+pub(crate) fn check_record_release(
+    record: &str,
+    min_release: i16,
+    release: i16,
+) -> Result<(), GdsError> {
+    if release < min_release {
+        return Err(GdsError::ValueOutOfRange(format!(
+            "{} record needs gds release {} or later, lib release is {}",
+            record, min_release, release
+        )));
+    }
+    Ok(())
+}
+
+// ascii string record, padded to even length
+fn string_record(record: &[u8], s: &str) -> Result<Vec<u8>, GdsError> {
+    let mut value = gds_writer::ascii_string_to_be_bytes(s)?;
+    if !value.len().is_multiple_of(2) {
+        value.push(0);
+    }
+    let mut data = Vec::<u8>::new();
+    data.extend((value.len() as i16 + 4_i16).to_be_bytes());
+    data.extend(record);
+    data.extend(value);
+    Ok(data)
+}
+
+// record of file names, each name is padded to 44 bytes with null chars
+fn file_names_record(record: &[u8], names: &[String]) -> Result<Vec<u8>, GdsError> {
+    // one record holds at most 744 names
+    let len = names.len() * 44 + 4;
+    if len > i16::MAX as usize {
+        return Err(GdsError::ValueOutOfRange(format!(
+            "Gds record can not hold more than {} file names, count: {}",
+            (i16::MAX as usize - 4) / 44,
+            names.len()
+        )));
+    }
+    let mut data = Vec::<u8>::new();
+    data.extend((len as i16).to_be_bytes());
+    data.extend(record);
+    for name in names {
+        let mut value = gds_writer::ascii_string_to_be_bytes(name)?;
+        if value.len() > 44 {
+            return Err(GdsError::ValueOutOfRange(format!(
+                "Gds file name can not exceed 44 chars: {}",
+                name
+            )));
+        }
+        value.resize(44, 0);
+        data.extend(value);
+    }
+    Ok(data)
+}
+
+impl Lib {
+    /// write lib to byte stream, compressed if `options.compression` is set
+    ///
+    /// structures are serialized and written one by one, so whole gds data is never held in memory
+    pub fn write_to<W: Write>(
+        &self,
+        writer: &mut W,
+        options: &WriteOptions,
+    ) -> Result<(), GdsError> {
+        let mut encoder = Encoder::new(writer, options.compression)?;
+        self.write_records(&mut encoder, options)?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    fn write_records<W: Write>(
+        &self,
+        writer: &mut W,
+        options: &WriteOptions,
+    ) -> Result<(), GdsError> {
+        self.check_version()?;
+        // raw records are put after lib header records and structures
+        let mut raw_writer = RawRecordWriter::new(&self.raw_records);
+        for record in split_records(&self.header_gds(&options.timestamps)?) {
+            raw_writer.record(writer, record)?;
+        }
+
+        // dump strucs
+        let scaling = self.units / self.precision;
+        for struc in self.ordered_strucs(options.struc_order) {
+            let struc = struc.read().unwrap();
+            struc.write_to(writer, scaling, options)?;
+            raw_writer.after(writer, &RawAnchor::Struc(struc.name.clone()))?;
+        }
+
+        // endlib
+        let mut endlib = Vec::<u8>::new();
+        endlib.extend(4_i16.to_be_bytes());
+        endlib.extend(gds_record::ENDLIB);
+        raw_writer.last_record(writer, &endlib)
+    }
+
+    // records from HEADER to UNITS
+    pub(crate) fn header_gds(&self, timestamps: &Timestamps) -> Result<Vec<u8>, GdsError> {
         let mut data = Vec::<u8>::new();
 
         // gds data binary format is in big endian
@@ -285,7 +561,7 @@ impl GdsObject for Lib {
         let mut header_data = Vec::<u8>::new();
 
         header_data.extend(gds_record::HEADER);
-        header_data.extend(GDS_VERSIOIN.to_be_bytes());
+        header_data.extend(self.version.to_be_bytes());
 
         data.extend((header_data.len() as i16 + 2_i16).to_be_bytes());
         data.extend(header_data);
@@ -293,16 +569,20 @@ impl GdsObject for Lib {
         // bgnlib and date
         let mut date_data = Vec::<u8>::new();
         date_data.extend(gds_record::BGNLIB);
-        date_data.extend(self.date.to_gds(0.0)?);
+        date_data.extend(timestamps.date(&self.date)?.to_gds(0.0)?);
 
         data.extend((date_data.len() as i16 + 2_i16).to_be_bytes());
         data.extend(date_data);
 
+        if let Some(srfname) = &self.srfname {
+            data.extend(string_record(gds_record::SRFNAME, srfname)?);
+        }
+
         // lib name
         let mut lib_name = Vec::<u8>::new();
         lib_name.extend(gds_record::LIBNAME);
-        let mut name = gds_writer::ascii_string_to_be_bytes(&self.name);
-        if name.len() % 2 != 0 {
+        let mut name = gds_writer::ascii_string_to_be_bytes(&self.name)?;
+        if !name.len().is_multiple_of(2) {
             name.push(0);
         }
         lib_name.extend(name);
@@ -310,30 +590,62 @@ impl GdsObject for Lib {
         data.extend((lib_name.len() as i16 + 2_i16).to_be_bytes());
         data.extend(lib_name);
 
+        // optional lib records, in order of gds stream syntax
+        if !self.reflibs.is_empty() {
+            data.extend(file_names_record(gds_record::REFLIBS, &self.reflibs)?);
+        }
+        if let Some(fonts) = &self.fonts {
+            data.extend(file_names_record(gds_record::FONTS, fonts)?);
+        }
+        if let Some(attrtable) = &self.attrtable {
+            if attrtable.len() > 44 {
+                return Err(GdsError::ValueOutOfRange(format!(
+                    "Gds attribute table file name can not exceed 44 chars: {}",
+                    attrtable
+                )));
+            }
+            data.extend(string_record(gds_record::ATTRTABLE, attrtable)?);
+        }
+        if let Some(generations) = self.generations {
+            if !(2..=99).contains(&generations) {
+                return Err(GdsError::ValueOutOfRange(format!(
+                    "Gds generations should be in range [2:99]: {}",
+                    generations
+                )));
+            }
+            data.extend(6_i16.to_be_bytes());
+            data.extend(gds_record::GENERATIONS);
+            data.extend(generations.to_be_bytes());
+        }
+        if let Some(format) = &self.format {
+            data.extend(6_i16.to_be_bytes());
+            data.extend(gds_record::FORMAT);
+            data.extend(format.format_type.to_be_bytes());
+            if !format.masks.is_empty() {
+                for mask in &format.masks {
+                    data.extend(string_record(gds_record::MASK, mask)?);
+                }
+                data.extend(4_i16.to_be_bytes());
+                data.extend(gds_record::ENDMASKS);
+            }
+        }
+
         // unit
         let mut unit_data = Vec::<u8>::new();
         unit_data.extend(gds_record::UNITS);
-        unit_data.extend(gds_writer::f64_to_gds_bytes(self.precision / self.units));
-        unit_data.extend(gds_writer::f64_to_gds_bytes(self.precision));
+        unit_data.extend(gds_writer::f64_to_gds_bytes(self.precision / self.units)?);
+        unit_data.extend(gds_writer::f64_to_gds_bytes(self.precision)?);
 
         data.extend((unit_data.len() as i16 + 2_i16).to_be_bytes());
         data.extend(unit_data);
+        Ok(data)
+    }
+}
 
-        let scaling = self.units / self.precision;
-
-        // dump strucs
-        for (_idx, uniq_struc) in self.uniq_struct.iter() {
-            let ref_c = uniq_struc.struct_address.0.clone();
-            let struc_bytes = ref_c.read().unwrap().to_gds(scaling)?;
-            data.extend(struc_bytes);
-        }
-
-        // endlib
-        let mut endlib_data = Vec::<u8>::new();
-        endlib_data.extend(gds_record::ENDLIB);
-
-        data.extend((endlib_data.len() as i16 + 2_i16).to_be_bytes());
-        data.extend(endlib_data);
+impl GdsObject for Lib {
+    fn to_gds(&self, _: f64) -> Result<Vec<u8>, GdsError> {
+        let mut data = Vec::<u8>::new();
+        self.write_records(&mut data, &WriteOptions::default())?;
         Ok(data)
     }
 }
@@ -353,17 +665,23 @@ mod test_lib {
         struc_1.write().unwrap().refs.push(ref_2);
 
         // add cross referd struct cause error, lib will be rewinded
-        assert!(matches!(lib.add_struc(&struc_1), Err(_)));
-        assert!(matches!(lib.add_struc(&struc_2), Err(_)));
-        assert!(lib.all_strucs().len() == 0);
+        assert!(matches!(
+            lib.add_struc(&struc_1),
+            Err(GdsError::CircularReference(path)) if path.len() == 3 && path[0] == path[2]
+        ));
+        assert!(lib.add_struc(&struc_2).is_err());
+        assert!(lib.all_strucs().is_empty());
     }
     #[test]
     fn test_lib_add_same_name_diff_struct_error() {
         let mut lib = Lib::new("test");
         let struc_1 = Arc::new(RwLock::new(Struc::new("test_1")));
         let struc_2 = Arc::new(RwLock::new(Struc::new("test_1")));
-        assert!(matches!(lib.add_struc(&struc_1), Ok(_)));
-        assert!(matches!(lib.add_struc(&struc_2), Err(_)));
+        assert!(lib.add_struc(&struc_1).is_ok());
+        assert!(matches!(
+            lib.add_struc(&struc_2),
+            Err(GdsError::DuplicateStructure(name)) if name == "test_1"
+        ));
         assert!(lib.all_strucs().len() == 1);
     }
 
@@ -385,15 +703,15 @@ mod test_lib {
         let ref_3 = Ref::new(&struc_4);
         struc_3.write().unwrap().refs.push(ref_3);
 
-        assert!(matches!(lib.add_struc(&struc_1), Ok(_)));
-        assert!(matches!(lib.add_struc(&struc_2), Ok(_)));
-        assert!(matches!(lib.add_struc(&struc_3), Ok(_)));
-        assert!(matches!(lib.add_struc(&struc_4), Ok(_)));
+        assert!(lib.add_struc(&struc_1).is_ok());
+        assert!(lib.add_struc(&struc_2).is_ok());
+        assert!(lib.add_struc(&struc_3).is_ok());
+        assert!(lib.add_struc(&struc_4).is_ok());
         let top_strucs = lib.top_strucs();
         assert!(top_strucs.len() == 2);
 
-        assert!(top_strucs.iter().any(|v| Arc::ptr_eq(&v, &struc_1)));
-        assert!(top_strucs.iter().any(|v| Arc::ptr_eq(&v, &struc_2)));
+        assert!(top_strucs.iter().any(|v| Arc::ptr_eq(v, &struc_1)));
+        assert!(top_strucs.iter().any(|v| Arc::ptr_eq(v, &struc_2)));
 
         assert!(lib.all_strucs().len() == 4);
     }
@@ -405,7 +723,7 @@ mod test_lib {
         let struc_2 = Arc::new(RwLock::new(Struc::new("test_2")));
         let ref_1 = Ref::new(&struc_2);
         struc_1.write().unwrap().refs.push(ref_1);
-        assert!(matches!(lib.add_struc(&struc_1), Ok(_)));
+        assert!(lib.add_struc(&struc_1).is_ok());
         assert!(lib.top_strucs().len() == 1);
         assert!(lib.all_strucs().len() == 2);
         // only remove struct1, now struc2 is top struc
@@ -414,4 +732,260 @@ mod test_lib {
         assert!(top_s.len() == 1);
         assert!(Arc::ptr_eq(&top_s[0], &struc_2));
     }
+
+    #[test]
+    fn test_lib_version() {
+        let mut lib = Lib::new("test");
+        let struc = Arc::new(RwLock::new(Struc::new("test_1")));
+        let path = Path {
+            points: vec![Points::new(0.0, 0.0), Points::new(1.0, 0.0)],
+            ..Default::default()
+        };
+        struc.write().unwrap().paths.push(path);
+        assert!(lib.add_struc(&struc).is_ok());
+
+        lib.version = 5;
+        let bytes = lib.gds_bytes().unwrap();
+        assert_eq!(bytes[..6], [0x00, 0x06, 0x00, 0x02, 0x00, 0x05]);
+        let read_lib = crate::read_gdsii_from(bytes.as_slice()).unwrap();
+        assert_eq!(read_lib.version, 5);
+
+        // path extension is not supported by release 3
+        struc.write().unwrap().paths[0].end_type = PathEndType::SquareExtend;
+        assert!(lib.gds_bytes().is_ok());
+        lib.version = 3;
+        assert!(matches!(
+            lib.gds_bytes(),
+            Err(GdsError::ValueOutOfRange(msg)) if msg.contains("BGNEXTN")
+        ));
+        lib.version = 601;
+        assert!(matches!(lib.gds_bytes(), Err(GdsError::ValueOutOfRange(_))));
+
+        // version not valid is rejected on reading, in strict mode only
+        lib.version = 5;
+        let mut bytes = lib.gds_bytes().unwrap();
+        bytes[4..6].copy_from_slice(&601_i16.to_be_bytes());
+        assert!(crate::read_gdsii_from(bytes.as_slice()).is_err());
+        let options = crate::ReadOptions {
+            strict: false,
+            ..Default::default()
+        };
+        let (read_lib, diagnostics) =
+            crate::read_gdsii_from_with_options(bytes.as_slice(), &options).unwrap();
+        assert_eq!(read_lib.version, 601);
+        assert!(diagnostics
+            .iter()
+            .any(|d| d.offset == 0 && d.message.contains("not valid gds version: 601")));
+    }
+
+    // writing `lib` fails with gds `version` before `min_version` needed by `record`
+    fn check_release_error(lib: &mut Lib, record: &str, version: i16, min_version: i16) {
+        lib.version = version;
+        assert!(matches!(
+            lib.gds_bytes(),
+            Err(GdsError::ValueOutOfRange(msg)) if msg.starts_with(record)
+        ));
+        lib.version = min_version;
+        assert!(lib.check_version().is_ok());
+    }
+
+    #[test]
+    fn test_lib_header_release() {
+        let mut lib = Lib::new("test");
+        lib.srfname = Some("rules".to_string());
+        check_release_error(&mut lib, "SRFNAME", 0, 3);
+        lib.srfname = None;
+        lib.reflibs = vec!["ref".to_string()];
+        check_release_error(&mut lib, "REFLIBS", 0, 3);
+        lib.reflibs.clear();
+        lib.fonts = Some(["f0", "f1", "f2", "f3"].map(String::from));
+        check_release_error(&mut lib, "FONTS", 0, 3);
+        lib.fonts = None;
+        lib.attrtable = Some("attrs".to_string());
+        check_release_error(&mut lib, "ATTRTABLE", 0, 3);
+        lib.attrtable = None;
+        lib.generations = Some(3);
+        check_release_error(&mut lib, "GENERATIONS", 0, 3);
+        lib.generations = None;
+        lib.format = Some(LibFormat {
+            format_type: 1,
+            masks: vec!["1 ; 0".to_string()],
+        });
+        check_release_error(&mut lib, "FORMAT", 3, 4);
+
+        // stream writer checks lib header records too
+        let options = WriteOptions::default();
+        lib.version = 3;
+        assert!(crate::GdsStreamWriter::new(Vec::new(), &lib, &options).is_err());
+        lib.version = 4;
+        assert!(crate::GdsStreamWriter::new(Vec::new(), &lib, &options).is_ok());
+    }
+
+    #[test]
+    fn test_struc_release() {
+        let mut lib = Lib::new("test");
+        let struc = Arc::new(RwLock::new(Struc::new("test_1")));
+        lib.add_struc(&struc).unwrap();
+        let polygon = || Polygon {
+            points: vec![
+                Points::new(0.0, 0.0),
+                Points::new(1.0, 0.0),
+                Points::new(0.0, 1.0),
+            ],
+            ..Default::default()
+        };
+
+        struc.write().unwrap().strclass = Some(1);
+        check_release_error(&mut lib, "STRCLASS", 4, 5);
+        struc.write().unwrap().strclass = None;
+        struc.write().unwrap().polygons.push(Polygon {
+            elflags: Some(ElFlags(1)),
+            ..polygon()
+        });
+        check_release_error(&mut lib, "ELFLAGS", 0, 3);
+        struc.write().unwrap().polygons[0] = Polygon {
+            plex: Some(Plex::new(1, true)),
+            ..polygon()
+        };
+        check_release_error(&mut lib, "PLEX", 4, 5);
+        struc.write().unwrap().polygons.clear();
+        struc.write().unwrap().nodes.push(Node::default());
+        check_release_error(&mut lib, "NODE", 0, 3);
+        struc.write().unwrap().nodes.clear();
+        struc.write().unwrap().boxes.push(GdsBox::default());
+        check_release_error(&mut lib, "BOX", 3, 4);
+
+        // stream writer checks elements
+        lib.version = 3;
+        let mut stream =
+            crate::GdsStreamWriter::new(Vec::new(), &lib, &WriteOptions::default()).unwrap();
+        stream.begin_struct("test_2").unwrap();
+        assert!(stream.gds_box(&GdsBox::default()).is_err());
+        assert!(stream
+            .polygon(&Polygon {
+                plex: Some(Plex::new(1, true)),
+                ..polygon()
+            })
+            .is_err());
+        let node = Node {
+            points: vec![Points::new(0.0, 0.0)],
+            ..Default::default()
+        };
+        assert!(stream.node(&node).is_ok());
+    }
+
+    #[test]
+    fn test_lib_header_records() {
+        let mut lib = Lib::new("test");
+        lib.reflibs = vec!["ref_a".to_string(), "ref_b".to_string()];
+        lib.fonts = Some(["f0", "f1", "f2", "f3"].map(String::from));
+        lib.attrtable = Some("attrs".to_string());
+        lib.generations = Some(3);
+        lib.format = Some(LibFormat {
+            format_type: 1,
+            masks: vec!["1-3 5 ; 0".to_string(), "7 ; 0-2".to_string()],
+        });
+        let bytes = lib.gds_bytes().unwrap();
+
+        let read_lib = crate::read_gdsii_from(bytes.as_slice()).unwrap();
+        assert_eq!(read_lib.reflibs, lib.reflibs);
+        assert_eq!(read_lib.fonts, lib.fonts);
+        assert_eq!(read_lib.attrtable, lib.attrtable);
+        assert_eq!(read_lib.generations, Some(3));
+        assert_eq!(read_lib.format, lib.format);
+        assert_eq!(read_lib.gds_bytes().unwrap(), bytes);
+
+        // GENERATIONS moved after UNITS
+        let generations: &[u8] = &[0x00, 0x06, 0x22, 0x02, 0x00, 0x03];
+        let offset = bytes.windows(6).position(|w| w == generations).unwrap();
+        let units = bytes.windows(4).position(|w| w == [0x00, 0x14, 0x03, 0x05]);
+        let units_end = units.unwrap() + 20;
+        let mut bytes = bytes;
+        bytes[offset..units_end].rotate_left(6);
+        assert!(matches!(
+            crate::read_gdsii_from(bytes.as_slice()),
+            Err(e) if e.root().to_string().contains("GENERATIONS record out of order")
+        ));
+        let options = crate::ReadOptions {
+            strict: false,
+            ..Default::default()
+        };
+        let (_, diagnostics) =
+            crate::read_gdsii_from_with_options(bytes.as_slice(), &options).unwrap();
+        assert!(diagnostics
+            .iter()
+            .any(|d| d.message.contains("GENERATIONS record out of order")));
+
+        lib.generations = Some(100);
+        assert!(matches!(lib.gds_bytes(), Err(GdsError::ValueOutOfRange(_))));
+
+        // REFLIBS record length is limited to i16
+        lib.generations = None;
+        lib.reflibs = (0..744).map(|i| format!("ref_{}", i)).collect();
+        let bytes = lib.gds_bytes().unwrap();
+        let read_lib = crate::read_gdsii_from(bytes.as_slice()).unwrap();
+        assert_eq!(read_lib.reflibs.len(), 744);
+        lib.reflibs.push("ref_744".to_string());
+        assert!(matches!(
+            lib.gds_bytes(),
+            Err(GdsError::ValueOutOfRange(msg)) if msg.contains("745")
+        ));
+    }
+
+    #[test]
+    fn test_struc_order() {
+        let top = Arc::new(RwLock::new(Struc::new("top")));
+        let b = Arc::new(RwLock::new(Struc::new("b")));
+        let a = Arc::new(RwLock::new(Struc::new("a")));
+        let leaf = Arc::new(RwLock::new(Struc::new("leaf")));
+        b.write().unwrap().refs.push(Ref::new(&leaf));
+        top.write().unwrap().refs.push(Ref::new(&b));
+        top.write().unwrap().refs.push(Ref::new(&a));
+        let mut polygon = Polygon::default();
+        for attr in [30, 2, 17, 5] {
+            polygon.property.0.insert(attr, format!("value{}", attr));
+        }
+        leaf.write().unwrap().polygons.push(polygon);
+
+        let mut lib = Lib::new("test");
+        lib.add_struc(&top).unwrap();
+        let names = |order| {
+            lib.ordered_strucs(order)
+                .iter()
+                .map(|s| s.read().unwrap().name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(StrucOrder::Insertion), ["top", "b", "leaf", "a"]);
+        assert_eq!(names(StrucOrder::Alphabetical), ["a", "b", "leaf", "top"]);
+        assert_eq!(names(StrucOrder::BottomUp), ["leaf", "b", "a", "top"]);
+
+        for struc_order in [
+            StrucOrder::Insertion,
+            StrucOrder::Alphabetical,
+            StrucOrder::BottomUp,
+        ] {
+            let options = WriteOptions {
+                struc_order,
+                ..Default::default()
+            };
+            let mut first = Vec::new();
+            lib.write_to(&mut first, &options).unwrap();
+            let mut second = Vec::new();
+            lib.write_to(&mut second, &options).unwrap();
+            assert_eq!(first, second);
+
+            let read_lib = crate::read_gdsii_from(first.as_slice()).unwrap();
+            assert_eq!(read_lib.all_strucs().len(), 4);
+        }
+
+        // properties are written in ascending attribute order
+        let data = leaf.read().unwrap().polygons[0].to_gds(1000.0).unwrap();
+        let mut attr_values = Vec::new();
+        for (i, w) in data.windows(4).enumerate() {
+            if w[..2] == [0x00, 0x06] && w[2..] == *gds_record::PROPATTR {
+                attr_values.push(i16::from_be_bytes([data[i + 4], data[i + 5]]));
+            }
+        }
+        assert_eq!(attr_values, [2, 5, 17, 30]);
+    }
 }
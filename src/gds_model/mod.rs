@@ -1,36 +1,73 @@
-use std::error::Error;
+use crate::gds_error::GdsError;
 
+mod angle;
 mod date;
+mod element;
+mod gds_box;
 mod library;
+mod node;
 mod path;
 mod points;
 mod polygon;
+mod property;
+mod raw_record;
 mod sref;
 mod struc;
 mod text;
+mod transform;
 mod vector;
-mod property;
 
+pub use self::angle::*;
 pub use self::date::*;
+pub use self::element::*;
+pub use self::gds_box::*;
 pub use self::library::*;
+pub use self::node::*;
 pub use self::path::*;
 pub use self::points::*;
 pub use self::polygon::*;
+pub use self::property::*;
+pub use self::raw_record::*;
 pub use self::sref::*;
 pub use self::struc::*;
 pub use self::text::*;
+pub use self::transform::*;
 pub use self::vector::*;
-pub use self::property::*;
 
-trait GdsObject {
-    fn to_gds(&self, scaling: f64) -> Result<Vec<u8>, Box<dyn Error>>;
+pub(crate) trait GdsObject {
+    fn to_gds(&self, scaling: f64) -> Result<Vec<u8>, GdsError>;
 }
 
 #[cfg(test)]
 mod test_gds_model {
     use super::*;
-    use std::sync::RwLock;
     use std::sync::Arc;
+    use std::sync::RwLock;
+
+    // lib "test" with structure "cell", elements are added by `fill`
+    pub(super) fn cell_lib(fill: impl FnOnce(&mut Struc)) -> (Lib, Arc<RwLock<Struc>>) {
+        let mut lib = Lib::new("test");
+        let struc = Arc::new(RwLock::new(Struc::new("cell")));
+        fill(&mut struc.write().unwrap());
+        lib.add_struc(&struc).unwrap();
+        (lib, struc)
+    }
+
+    // write `lib` and read it back, lib read is written to same bytes
+    pub(super) fn round_trip(lib: &Lib) -> (Vec<u8>, Box<Lib>) {
+        let bytes = lib.gds_bytes().unwrap();
+        let read_lib = crate::read_gdsii_from(bytes.as_slice()).unwrap();
+        assert_eq!(read_lib.gds_bytes().unwrap(), bytes);
+        (bytes, read_lib)
+    }
+
+    // structure named `name` of `lib`
+    pub(super) fn struc_of(lib: &Lib, name: &str) -> Arc<RwLock<Struc>> {
+        lib.all_strucs()
+            .into_iter()
+            .find(|s| s.read().unwrap().name == name)
+            .unwrap()
+    }
 
     #[test]
     fn test_lib_top_struc() {
@@ -50,4 +87,30 @@ mod test_gds_model {
         assert_eq!(top_struc.len(), 1);
         assert!(Arc::ptr_eq(&top_struc[0], &struc1));
     }
+
+    #[test]
+    fn test_path_extension_round_trip() {
+        let mut gds_lib = Lib::new("test");
+        let struc = Arc::new(RwLock::new(Struc::new("cell")));
+        let path = Path {
+            width: 0.2,
+            end_type: PathEndType::SquareExtend,
+            points: vec![Points::new(0.0, 0.0), Points::new(10.0, 0.0)],
+            extend_begin: 0.5,
+            extend_end: -0.25,
+            ..Default::default()
+        };
+        struc.write().unwrap().paths.push(path);
+        gds_lib.add_struc(&struc).unwrap();
+        let bytes = gds_lib.gds_bytes().unwrap();
+
+        let read_lib = crate::read_gdsii_from(bytes.as_slice()).unwrap();
+        let read_struc = read_lib.all_strucs()[0].clone();
+        let read_struc = read_struc.read().unwrap();
+        let read_path = &read_struc.paths[0];
+        assert!(matches!(read_path.end_type, PathEndType::SquareExtend));
+        assert_eq!(read_path.extend_begin, 0.5);
+        assert_eq!(read_path.extend_end, -0.25);
+        assert_eq!(read_lib.gds_bytes().unwrap(), bytes);
+    }
 }
@@ -0,0 +1,103 @@
+use super::*;
+use crate::gds_record;
+
+/// Gds Node, electrical net annotation
+#[derive(Default, Debug)]
+pub struct Node {
+    pub layer: i16,
+    pub nodetype: i16,
+    /// 1 to 50 points
+    pub points: Vec<Points>,
+    pub property: Property,
+    /// ELFLAGS record of element
+    pub elflags: Option<ElFlags>,
+    /// PLEX record of element
+    pub plex: Option<Plex>,
+    /// records not decoded, written back at their position in element
+    pub raw_records: Vec<RawRecord>,
+}
+
+impl GdsObject for Node {
+    fn to_gds(&self, scaling: f64) -> Result<Vec<u8>, GdsError> {
+        let mut data = Vec::<u8>::new();
+
+        // node
+        data.extend(4_i16.to_be_bytes());
+        data.extend(gds_record::NODE);
+
+        // elflags and plex
+        data.extend(element_flags_to_gds(&self.elflags, &self.plex)?);
+
+        // layer
+        data.extend(6_i16.to_be_bytes());
+        data.extend(gds_record::LAYER);
+        data.extend(self.layer.to_be_bytes());
+
+        // nodetype
+        data.extend(6_i16.to_be_bytes());
+        data.extend(gds_record::NODETYPE);
+        data.extend(self.nodetype.to_be_bytes());
+
+        // points
+        if self.points.is_empty() || self.points.len() > 50 {
+            return Err(GdsError::ValueOutOfRange(format!(
+                "Gds node should have 1 to 50 points, found {}",
+                self.points.len()
+            )));
+        }
+        data.extend((4_i16 + 8 * self.points.len() as i16).to_be_bytes());
+        data.extend(gds_record::XY);
+        self.points.iter().for_each(|point| {
+            let x = point.x * scaling;
+            let y = point.y * scaling;
+            data.extend((f64::round(x) as i32).to_be_bytes());
+            data.extend((f64::round(y) as i32).to_be_bytes());
+        });
+
+        // properties
+        data.extend(self.property.to_gds(scaling)?);
+
+        // endel
+        data.extend(4_i16.to_be_bytes());
+        data.extend(gds_record::ENDEL);
+
+        with_raw_records(data, &self.raw_records)
+    }
+}
+
+#[cfg(test)]
+mod test_node {
+    use super::*;
+    use crate::gds_model::test_gds_model::*;
+
+    #[test]
+    fn test_node_round_trip() {
+        let mut node = Node {
+            layer: 3,
+            nodetype: 7,
+            points: vec![Points::new(0.0, 0.0), Points::new(1.5, 2.0)],
+            ..Default::default()
+        };
+        node.property.0.insert(1, "net_a".to_string());
+        let (lib, struc) = cell_lib(|s| s.nodes.push(node));
+        let (_, read_lib) = round_trip(&lib);
+
+        let read_struc = struc_of(&read_lib, "cell");
+        let read_struc = read_struc.read().unwrap();
+        assert_eq!(read_struc.nodes.len(), 1);
+        let read_node = &read_struc.nodes[0];
+        assert_eq!(read_node.layer, 3);
+        assert_eq!(read_node.nodetype, 7);
+        let points = read_node
+            .points
+            .iter()
+            .map(|p| (p.x, p.y))
+            .collect::<Vec<_>>();
+        assert_eq!(points, [(0.0, 0.0), (1.5, 2.0)]);
+        assert_eq!(read_node.property.0[&1], "net_a");
+
+        // node without point can not be written
+        struc.write().unwrap().nodes[0].points.clear();
+        assert!(matches!(lib.gds_bytes(), Err(GdsError::ValueOutOfRange(_))));
+    }
+}
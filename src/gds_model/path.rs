@@ -1,31 +1,26 @@
 use super::*;
-use crate::gds_error;
 use crate::gds_record;
+use crate::WriteOptions;
 
 #[repr(i16)]
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum PathEndType {
+    #[default]
     Square = 0,
     Round = 1,
     SquareHalfWidth = 2,
     SquareExtend = 4,
 }
 
-impl Default for PathEndType {
-    fn default() -> Self {
-        PathEndType::Square
-    }
-}
-
 impl TryFrom<&i16> for PathEndType {
-    type Error = gds_error::GDSIIError;
+    type Error = GdsError;
     fn try_from(value: &i16) -> Result<Self, Self::Error> {
         match value {
             0 => Ok(PathEndType::Square),
             1 => Ok(PathEndType::Round),
             2 => Ok(PathEndType::SquareHalfWidth),
             4 => Ok(PathEndType::SquareExtend),
-            _ => Err(gds_err!(&format!(
+            _ => Err(GdsError::InvalidRecordData(format!(
                 "not valid path end type value: {}",
                 value
             ))),
@@ -53,27 +48,95 @@ pub struct Path {
     pub end_type: PathEndType,
     pub points: Vec<Points>,
     pub property: Property,
+    /// ELFLAGS record of element
+    pub elflags: Option<ElFlags>,
+    /// PLEX record of element
+    pub plex: Option<Plex>,
+    /// records not decoded, written back at their position in element
+    pub raw_records: Vec<RawRecord>,
+    /// extension of path at begin, may be negative, only used by `SquareExtend` end type
     pub extend_begin: f64,
+    /// extension of path at end, may be negative, only used by `SquareExtend` end type
     pub extend_end: f64,
 }
 
-impl GdsObject for Path {
-    fn to_gds(&self, scaling: f64) -> Result<Vec<u8>, Box<dyn Error>> {
+impl Path {
+    pub(crate) fn check_release(&self, release: i16) -> Result<(), GdsError> {
+        if let PathEndType::SquareExtend = self.end_type {
+            // path extension is introduced in release 5
+            check_record_release("BGNEXTN", 5, release)?;
+        }
+        Ok(())
+    }
+}
+
+impl Path {
+    /// write path, split into segments sharing their joint points if it has more points than
+    /// limit of options
+    ///
+    /// extensions are kept at begin and end of whole path, joints of segments have no extension
+    pub(crate) fn to_gds_limited(
+        &self,
+        scaling: f64,
+        options: &WriteOptions,
+    ) -> Result<Vec<u8>, GdsError> {
+        let limit = options.points_limit();
+        if self.points.len() <= limit {
+            return self.to_gds(scaling);
+        }
+        if !options.split_large || limit < 2 {
+            return Err(GdsError::ValueOutOfRange(format!(
+                "Gds path can not have points more than {}, count: {}",
+                limit,
+                self.points.len()
+            )));
+        }
+        let mut data = Vec::<u8>::new();
+        let mut start = 0;
+        while start + 1 < self.points.len() {
+            let end = (start + limit).min(self.points.len());
+            let extend_begin = if start == 0 { self.extend_begin } else { 0.0 };
+            let extend_end = if end == self.points.len() {
+                self.extend_end
+            } else {
+                0.0
+            };
+            data.extend(self.gds_with_points(
+                &self.points[start..end],
+                extend_begin,
+                extend_end,
+                scaling,
+            )?);
+            start = end - 1;
+        }
+        Ok(data)
+    }
+
+    fn gds_with_points(
+        &self,
+        points: &[Points],
+        extend_begin: f64,
+        extend_end: f64,
+        scaling: f64,
+    ) -> Result<Vec<u8>, GdsError> {
         let mut data = Vec::<u8>::new();
 
         // path
         data.extend(4_i16.to_be_bytes());
         data.extend(gds_record::PATH);
 
+        // elflags and plex
+        data.extend(element_flags_to_gds(&self.elflags, &self.plex)?);
+
         // layer
         data.extend(6_i16.to_be_bytes());
         data.extend(gds_record::LAYER);
-        data.extend((self.layer as i16).to_be_bytes());
+        data.extend(self.layer.to_be_bytes());
 
         // datatype
         data.extend(6_i16.to_be_bytes());
         data.extend(gds_record::DATATYPE);
-        data.extend((self.datatype as i16).to_be_bytes());
+        data.extend(self.datatype.to_be_bytes());
 
         // endtype
         data.extend(6_i16.to_be_bytes());
@@ -88,21 +151,14 @@ impl GdsObject for Path {
         if let PathEndType::SquareExtend = self.end_type {
             data.extend(8_i16.to_be_bytes());
             data.extend(gds_record::BGNEXTN);
-            data.extend((f64::round(self.extend_begin * scaling) as u32).to_be_bytes());
+            data.extend((f64::round(extend_begin * scaling) as i32).to_be_bytes());
             data.extend(8_i16.to_be_bytes());
             data.extend(gds_record::ENDEXTN);
-            data.extend((f64::round(self.extend_end * scaling) as u32).to_be_bytes());
+            data.extend((f64::round(extend_end * scaling) as i32).to_be_bytes());
         }
 
         // points
-        data.extend((4_i16 + 8 * self.points.len() as i16).to_be_bytes());
-        data.extend(gds_record::XY);
-        self.points.iter().for_each(|point| {
-            let x = point.x * scaling;
-            let y = point.y * scaling;
-            data.extend((f64::round(x) as i32).to_be_bytes());
-            data.extend((f64::round(y) as i32).to_be_bytes());
-        });
+        data.extend(xy_record(points, false, scaling)?);
 
         // properties
         data.extend(self.property.to_gds(scaling)?);
@@ -111,6 +167,12 @@ impl GdsObject for Path {
         data.extend(4_i16.to_be_bytes());
         data.extend(gds_record::ENDEL);
 
-        Ok(data)
+        with_raw_records(data, &self.raw_records)
+    }
+}
+
+impl GdsObject for Path {
+    fn to_gds(&self, scaling: f64) -> Result<Vec<u8>, GdsError> {
+        self.gds_with_points(&self.points, self.extend_begin, self.extend_end, scaling)
     }
 }
@@ -1,3 +1,5 @@
+use super::*;
+use crate::gds_record;
 
 /// geometry coord, in Lib units
 #[derive(Default, Debug)]
@@ -10,4 +12,26 @@ impl Points {
     pub fn new(x: f64, y: f64) -> Self {
         Points { x, y }
     }
-}
\ No newline at end of file
+}
+
+// most points of XY record, record length can not exceed 65535 bytes
+pub(crate) const MAX_XY_POINTS: usize = 8191;
+
+// XY record of points in db units, first point is repeated at end if `close`
+pub(crate) fn xy_record(points: &[Points], close: bool, scaling: f64) -> Result<Vec<u8>, GdsError> {
+    let count = points.len() + (close && !points.is_empty()) as usize;
+    if count > MAX_XY_POINTS {
+        return Err(GdsError::ValueOutOfRange(format!(
+            "Gds XY record can not have points more than {}, count: {}",
+            MAX_XY_POINTS, count
+        )));
+    }
+    let mut data = Vec::<u8>::with_capacity(4 + 8 * count);
+    data.extend((4_u16 + 8 * count as u16).to_be_bytes());
+    data.extend(gds_record::XY);
+    for point in points.iter().chain(points.first().filter(|_| close)) {
+        data.extend((f64::round(point.x * scaling) as i32).to_be_bytes());
+        data.extend((f64::round(point.y * scaling) as i32).to_be_bytes());
+    }
+    Ok(data)
+}
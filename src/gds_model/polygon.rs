@@ -1,5 +1,6 @@
 use super::*;
 use crate::gds_record;
+use crate::WriteOptions;
 
 #[derive(Default, Debug)]
 pub struct Polygon {
@@ -8,46 +9,64 @@ pub struct Polygon {
     pub points: Vec<Points>,
     /// gds property, key is int value, value is max 128 bytes length ASCII str
     pub property: Property,
+    /// ELFLAGS record of element
+    pub elflags: Option<ElFlags>,
+    /// PLEX record of element
+    pub plex: Option<Plex>,
+    /// records not decoded, written back at their position in element
+    pub raw_records: Vec<RawRecord>,
 }
 
-impl GdsObject for Polygon {
-    fn to_gds(&self, scaling: f64) -> Result<Vec<u8>, Box<dyn Error>> {
+impl Polygon {
+    /// write polygon, split into pieces if it has more points than limit of options
+    ///
+    /// each piece keeps layer, datatype, properties and records of the polygon
+    pub(crate) fn to_gds_limited(
+        &self,
+        scaling: f64,
+        options: &WriteOptions,
+    ) -> Result<Vec<u8>, GdsError> {
+        let limit = options.points_limit();
+        // gds polygon points front is same as end
+        if self.points.len() < limit {
+            return self.to_gds(scaling);
+        }
+        if !options.split_large || limit < 4 {
+            return Err(GdsError::ValueOutOfRange(format!(
+                "Gds polygon can not have points more than {}, count: {}",
+                limit,
+                self.points.len() + 1
+            )));
+        }
+        let mut data = Vec::<u8>::new();
+        for piece in fracture(&self.points, limit - 1, scaling)? {
+            data.extend(self.gds_with_points(&piece, scaling)?);
+        }
+        Ok(data)
+    }
+
+    fn gds_with_points(&self, points: &[Points], scaling: f64) -> Result<Vec<u8>, GdsError> {
         let mut data = Vec::<u8>::new();
 
         // boundary
         data.extend(4_i16.to_be_bytes());
         data.extend(gds_record::BOUNDARY);
 
+        // elflags and plex
+        data.extend(element_flags_to_gds(&self.elflags, &self.plex)?);
+
         // layer
         data.extend(6_i16.to_be_bytes());
         data.extend(gds_record::LAYER);
-        data.extend((self.layer as i16).to_be_bytes());
+        data.extend(self.layer.to_be_bytes());
 
         // datatype
         data.extend(6_i16.to_be_bytes());
         data.extend(gds_record::DATATYPE);
-        data.extend((self.datatype as i16).to_be_bytes());
+        data.extend(self.datatype.to_be_bytes());
 
-        // points
-        if self.points.len() > 8190 {
-            gds_err!(&format!(
-                "Gds polygons can not have points more than 8190 count:{:#?}",
-                &self
-            ));
-        }
-        // gds polygon points front is same as end
-        data.extend((4_i16 + 8 * (self.points.len() + 1) as i16).to_be_bytes());
-        data.extend(gds_record::XY);
-        self.points.iter().for_each(|point| {
-            let x = point.x * scaling;
-            let y = point.y * scaling;
-            data.extend((f64::round(x) as i32).to_be_bytes());
-            data.extend((f64::round(y) as i32).to_be_bytes());
-        });
-        if !self.points.is_empty() {
-            data.extend((f64::round(self.points[0].x * scaling) as i32).to_be_bytes());
-            data.extend((f64::round(self.points[0].y * scaling) as i32).to_be_bytes());
-        }
+        // points, gds polygon points front is same as end
+        data.extend(xy_record(points, true, scaling)?);
 
         // properties
         data.extend(self.property.to_gds(scaling)?);
@@ -55,6 +74,438 @@ impl GdsObject for Polygon {
         // endelement
         data.extend(4_i16.to_be_bytes());
         data.extend(gds_record::ENDEL);
-        Ok(data)
+        with_raw_records(data, &self.raw_records)
+    }
+}
+
+impl GdsObject for Polygon {
+    fn to_gds(&self, scaling: f64) -> Result<Vec<u8>, GdsError> {
+        self.gds_with_points(&self.points, scaling)
+    }
+}
+
+// cut polygon along vertical or horizontal lines until each piece has at most `max_vertices`
+//
+// cutting is done in database units, cut lines and cut points are snapped to the grid, so
+// pieces still share their boundaries after points are rounded in XY record
+fn fracture(
+    points: &[Points],
+    max_vertices: usize,
+    scaling: f64,
+) -> Result<Vec<Vec<Points>>, GdsError> {
+    let mut pieces = Vec::new();
+    let mut polygons = vec![points
+        .iter()
+        .map(|p| ((p.x * scaling).round(), (p.y * scaling).round()))
+        .collect::<Vec<_>>()];
+    while let Some(polygon) = polygons.pop() {
+        if polygon.len() <= max_vertices {
+            pieces.push(
+                polygon
+                    .into_iter()
+                    .map(|(x, y)| Points::new(x / scaling, y / scaling))
+                    .collect(),
+            );
+            continue;
+        }
+        // cut along the longer side of bounding box first
+        let (width, height) = (extent(&polygon, 0), extent(&polygon, 1));
+        let axes = if width >= height { [0, 1] } else { [1, 0] };
+        let parts = axes.into_iter().find_map(|axis| {
+            let cut = cut_line(&polygon, axis)?;
+            let mut parts = split(&polygon, axis, cut, true);
+            parts.extend(split(&polygon, axis, cut, false));
+            parts
+                .iter()
+                .all(|p| p.len() < polygon.len())
+                .then_some(parts)
+        });
+        let Some(parts) = parts else {
+            return Err(GdsError::ValueOutOfRange(format!(
+                "Gds polygon can not be split into pieces of {} vertices",
+                max_vertices
+            )));
+        };
+        polygons.extend(parts.into_iter().filter(|p| area(p) != 0.0));
+    }
+    Ok(pieces)
+}
+
+fn coord(point: &(f64, f64), axis: usize) -> f64 {
+    if axis == 0 {
+        point.0
+    } else {
+        point.1
+    }
+}
+
+fn extent(polygon: &[(f64, f64)], axis: usize) -> f64 {
+    let coords = polygon.iter().map(|p| coord(p, axis));
+    coords.clone().fold(f64::MIN, f64::max) - coords.fold(f64::MAX, f64::min)
+}
+
+// cut at median of vertex coords on the grid, inside bounding box
+fn cut_line(polygon: &[(f64, f64)], axis: usize) -> Option<f64> {
+    let mut coords = polygon.iter().map(|p| coord(p, axis)).collect::<Vec<_>>();
+    coords.sort_by(f64::total_cmp);
+    coords.dedup();
+    match coords.len() {
+        0 | 1 => None,
+        2 => Some(((coords[0] + coords[1]) / 2.0).round()),
+        n => Some(coords[n / 2]),
+    }
+}
+
+// parts of polygon at low or high side of cut line, concave polygon may give several parts
+fn split(polygon: &[(f64, f64)], axis: usize, cut: f64, low: bool) -> Vec<Vec<(f64, f64)>> {
+    // 1 at the side to keep, -1 at the other side, 0 on cut line
+    let side = |p: &(f64, f64)| {
+        let side = match coord(p, axis).partial_cmp(&cut) {
+            Some(std::cmp::Ordering::Less) => 1,
+            Some(std::cmp::Ordering::Greater) => -1,
+            _ => 0,
+        };
+        if low {
+            side
+        } else {
+            -side
+        }
+    };
+
+    // vertices with crossing points of edges and cut line
+    let mut ring = Vec::with_capacity(polygon.len());
+    for (idx, a) in polygon.iter().enumerate() {
+        let b = &polygon[(idx + 1) % polygon.len()];
+        ring.push(*a);
+        if side(a) * side(b) < 0 {
+            // same edge is cut in same direction for both sides, so parts share the point
+            let (ca, cb) = (coord(a, axis), coord(b, axis));
+            let t = (cut - ca) / (cb - ca);
+            ring.push(if axis == 0 {
+                (cut, (a.1 + t * (b.1 - a.1)).round())
+            } else {
+                ((a.0 + t * (b.0 - a.0)).round(), cut)
+            });
+        }
+    }
+
+    // edge from vertex `idx` is kept if it has an end at the side, no edge crosses cut line now
+    let n = ring.len();
+    let kept = |idx: usize| side(&ring[idx]) > 0 || side(&ring[(idx + 1) % n]) > 0;
+    let Some(first) = (0..n).find(|&idx| !kept(idx)) else {
+        return if n > 0 { vec![ring] } else { Vec::new() };
+    };
+    // chains of kept edges, from a point on cut line to a point on cut line
+    let mut chains = Vec::<Vec<(f64, f64)>>::new();
+    let mut chain = Vec::new();
+    for step in 1..=n {
+        let idx = (first + step) % n;
+        if kept(idx) {
+            if chain.is_empty() {
+                chain.push(ring[idx]);
+            }
+            chain.push(ring[(idx + 1) % n]);
+        } else if !chain.is_empty() {
+            chains.push(std::mem::take(&mut chain));
+        }
+    }
+
+    // along cut line, part is at left side of its counterclockwise boundary, so boundary goes
+    // one way from end of a chain to start of a chain, segments on cut line do not overlap,
+    // so n-th chain end goes to n-th chain start in that direction
+    let forward = (area(polygon) > 0.0) == ((axis == 0) == low);
+    let along = |p: &(f64, f64)| {
+        let t = coord(p, 1 - axis);
+        if forward {
+            t
+        } else {
+            -t
+        }
+    };
+    let mut ends = (0..chains.len()).collect::<Vec<_>>();
+    let mut starts = ends.clone();
+    let end = |idx: usize| along(chains[idx].last().unwrap());
+    let start = |idx: usize| along(&chains[idx][0]);
+    ends.sort_by(|&a, &b| end(a).total_cmp(&end(b)));
+    starts.sort_by(|&a, &b| start(a).total_cmp(&start(b)));
+    let mut next = vec![0; chains.len()];
+    for (end, start) in ends.into_iter().zip(starts) {
+        next[end] = start;
+    }
+
+    // join chains into parts
+    let mut visited = vec![false; chains.len()];
+    let mut parts = Vec::new();
+    for first in 0..chains.len() {
+        let mut part = Vec::new();
+        let mut idx = first;
+        while !visited[idx] {
+            visited[idx] = true;
+            part.extend_from_slice(&chains[idx]);
+            idx = next[idx];
+        }
+        part.dedup();
+        while part.len() > 1 && part.first() == part.last() {
+            part.pop();
+        }
+        if !part.is_empty() {
+            parts.push(part);
+        }
+    }
+    parts
+}
+
+fn area(polygon: &[(f64, f64)]) -> f64 {
+    let mut area = 0.0;
+    for (idx, a) in polygon.iter().enumerate() {
+        let b = &polygon[(idx + 1) % polygon.len()];
+        area += a.0 * b.1 - b.0 * a.1;
+    }
+    area / 2.0
+}
+
+#[cfg(test)]
+mod test_polygon {
+    use super::*;
+
+    // U shape, notch from y=1 to top
+    const U_SHAPE: [(f64, f64); 8] = [
+        (0.0, 0.0),
+        (3.0, 0.0),
+        (3.0, 3.0),
+        (2.0, 3.0),
+        (2.0, 1.0),
+        (1.0, 1.0),
+        (1.0, 3.0),
+        (0.0, 3.0),
+    ];
+
+    // no edge touches or crosses a non-adjacent edge
+    fn is_simple(polygon: &[(f64, f64)]) -> bool {
+        let n = polygon.len();
+        let edge = |i: usize| (polygon[i], polygon[(i + 1) % n]);
+        let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| {
+            (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+        };
+        let on_segment = |p: (f64, f64), (a, b): ((f64, f64), (f64, f64))| {
+            cross(a, b, p) == 0.0
+                && p.0 >= a.0.min(b.0)
+                && p.0 <= a.0.max(b.0)
+                && p.1 >= a.1.min(b.1)
+                && p.1 <= a.1.max(b.1)
+        };
+        (0..n).all(|i| {
+            (i + 2..n).all(|j| {
+                if i == 0 && j == n - 1 {
+                    return true;
+                }
+                let (a, b) = edge(i);
+                let (c, d) = edge(j);
+                let (d1, d2) = (cross(a, b, c), cross(a, b, d));
+                let (d3, d4) = (cross(c, d, a), cross(c, d, b));
+                let proper = d1 * d2 < 0.0 && d3 * d4 < 0.0;
+                let touch = on_segment(c, (a, b))
+                    || on_segment(d, (a, b))
+                    || on_segment(a, (c, d))
+                    || on_segment(b, (c, d));
+                !proper && !touch
+            })
+        })
+    }
+
+    #[test]
+    fn test_split_u_shape() {
+        // cut through both arms of U
+        let high = split(&U_SHAPE, 1, 2.0, false);
+        assert_eq!(high.len(), 2);
+        assert!(high.iter().all(|p| p.len() == 4 && area(p) == 1.0));
+        let low = split(&U_SHAPE, 1, 2.0, true);
+        assert_eq!(low.len(), 1);
+        assert_eq!(area(&low[0]), 5.0);
+        assert!(is_simple(&low[0]));
+
+        // clockwise U gives same parts
+        let mut clockwise = U_SHAPE.to_vec();
+        clockwise.reverse();
+        let high = split(&clockwise, 1, 2.0, false);
+        assert_eq!(high.len(), 2);
+        assert!(high.iter().all(|p| p.len() == 4 && area(p) == -1.0));
+        assert_eq!(split(&clockwise, 1, 2.0, true).len(), 1);
+
+        // cut through bottom of U, and at vertical line
+        assert_eq!(split(&U_SHAPE, 1, 0.5, false).len(), 1);
+        assert_eq!(split(&U_SHAPE, 0, 1.5, true).len(), 1);
+        assert_eq!(split(&U_SHAPE, 0, 1.5, false).len(), 1);
+    }
+
+    #[test]
+    fn test_fracture_concave() {
+        // comb with teeth up, and rotated to have teeth at right
+        let teeth = 20;
+        let mut comb = vec![(0.0, 0.0), (2.0 * teeth as f64 - 1.0, 0.0)];
+        for i in (0..teeth).rev() {
+            let (left, right) = (2.0 * i as f64, 2.0 * i as f64 + 1.0);
+            comb.extend([(right, 3.0), (left, 3.0)]);
+            if i > 0 {
+                comb.extend([(left, 1.0), (left - 1.0, 1.0)]);
+            }
+        }
+        let rotated = comb.iter().map(|&(x, y)| (-y, x)).collect::<Vec<_>>();
+        for polygon in [comb, rotated] {
+            let expected = area(&polygon);
+            assert_eq!(expected, 4.0 * teeth as f64 - 1.0);
+            let points = polygon
+                .iter()
+                .map(|&(x, y)| Points::new(x, y))
+                .collect::<Vec<_>>();
+            let pieces = fracture(&points, 8, 1000.0).unwrap();
+            let mut total = 0.0;
+            for piece in pieces {
+                let piece = piece.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>();
+                assert!(piece.len() <= 8);
+                assert!(is_simple(&piece), "{:?}", piece);
+                total += area(&piece);
+            }
+            assert_eq!(total, expected);
+        }
+    }
+
+    #[test]
+    fn test_fracture_diagonal_on_grid() {
+        // star has diagonal edges, cut points on them are off the grid before snapping
+        let scaling = 10.0;
+        let points = (0..41)
+            .map(|i| {
+                let a = i as f64 / 41.0 * std::f64::consts::TAU + 0.1;
+                let r = 6.0 + (i * 37 % 11) as f64 * 0.37;
+                Points::new(r * a.cos(), r * a.sin())
+            })
+            .collect::<Vec<_>>();
+        let pieces = fracture(&points, 8, scaling).unwrap();
+        assert!(pieces.len() > 1);
+        // points are on the grid, rounding in XY record does not move them
+        let pieces = pieces
+            .iter()
+            .map(|piece| {
+                assert!(piece.len() <= 8);
+                piece
+                    .iter()
+                    .map(|p| {
+                        let (x, y) = (p.x * scaling, p.y * scaling);
+                        assert!((x - x.round()).abs() < 1e-9 && (y - y.round()).abs() < 1e-9);
+                        (x.round(), y.round())
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // no grid cell center is inside two pieces after rounding
+        let inside = |polygon: &[(f64, f64)], (x, y): (f64, f64)| {
+            let mut inside = false;
+            for (i, a) in polygon.iter().enumerate() {
+                let b = polygon[(i + 1) % polygon.len()];
+                if (a.1 > y) != (b.1 > y) && x < a.0 + (y - a.1) / (b.1 - a.1) * (b.0 - a.0) {
+                    inside = !inside;
+                }
+            }
+            inside
+        };
+        for x in -104..104 {
+            for y in -104..104 {
+                let center = (x as f64 + 0.5, y as f64 + 0.5);
+                let count = pieces.iter().filter(|p| inside(p, center)).count();
+                assert!(count <= 1, "{:?} is inside {} pieces", center, count);
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_xy_records() {
+        use std::sync::{Arc, RwLock};
+
+        let circle = |count: usize| {
+            (0..count)
+                .map(|i| {
+                    let a = i as f64 / count as f64 * std::f64::consts::TAU;
+                    Points::new(100.0 * a.cos(), 100.0 * a.sin())
+                })
+                .collect::<Vec<_>>()
+        };
+        let area = |points: &[Points]| {
+            let mut area = 0.0;
+            for (i, a) in points.iter().enumerate() {
+                let b = &points[(i + 1) % points.len()];
+                area += a.x * b.y - b.x * a.y;
+            }
+            area / 2.0
+        };
+
+        // too many points is error instead of broken XY record
+        let mut gds_lib = Lib::new("test");
+        let top = Arc::new(RwLock::new(Struc::new("top")));
+        top.write().unwrap().polygons.push(Polygon {
+            points: circle(9000),
+            ..Default::default()
+        });
+        gds_lib.add_struc(&top).unwrap();
+        assert!(matches!(
+            gds_lib.gds_bytes(),
+            Err(GdsError::ValueOutOfRange(_))
+        ));
+
+        // polygon and path are split with smaller limit
+        let mut gds_lib = Lib::new("test");
+        let top = Arc::new(RwLock::new(Struc::new("top")));
+        let points = circle(1000);
+        let expected_area = area(&points);
+        top.write().unwrap().polygons.push(Polygon {
+            points,
+            ..Default::default()
+        });
+        top.write().unwrap().paths.push(Path {
+            points: circle(1000),
+            width: 1.0,
+            end_type: PathEndType::SquareExtend,
+            extend_begin: 0.5,
+            extend_end: 0.25,
+            ..Default::default()
+        });
+        gds_lib.add_struc(&top).unwrap();
+        let options = WriteOptions {
+            max_points: 200,
+            ..Default::default()
+        };
+        let mut bytes = Vec::new();
+        assert!(gds_lib.write_to(&mut bytes, &options).is_err());
+        let options = WriteOptions {
+            split_large: true,
+            ..options
+        };
+        let mut bytes = Vec::new();
+        gds_lib.write_to(&mut bytes, &options).unwrap();
+
+        let read_lib = crate::read_gdsii_from(bytes.as_slice()).unwrap();
+        let read_top = read_lib.top_strucs()[0].clone();
+        let read_top = read_top.read().unwrap();
+        assert!(read_top.polygons.len() > 1);
+        assert!(read_top.polygons.iter().all(|p| p.points.len() < 200));
+        let read_area = read_top
+            .polygons
+            .iter()
+            .map(|p| area(&p.points))
+            .sum::<f64>();
+        assert!((read_area - expected_area).abs() < 1.0);
+
+        assert_eq!(read_top.paths.len(), 6);
+        assert!(read_top.paths.iter().all(|p| p.points.len() <= 200));
+        let ends = read_top
+            .paths
+            .iter()
+            .map(|p| (p.extend_begin, p.extend_end))
+            .collect::<Vec<_>>();
+        assert_eq!(ends[0], (0.5, 0.0));
+        assert_eq!(ends[5], (0.0, 0.25));
+        let count = read_top.paths.iter().map(|p| p.points.len()).sum::<usize>();
+        assert_eq!(count, 1000 + 5);
     }
 }
@@ -8,31 +8,33 @@ use std::collections::HashMap;
 pub struct Property(pub HashMap<i16, String>);
 
 impl GdsObject for Property {
-    fn to_gds(&self, _: f64) -> Result<Vec<u8>, Box<dyn Error>> {
+    fn to_gds(&self, _: f64) -> Result<Vec<u8>, GdsError> {
         let mut data = Vec::<u8>::new();
-        // properties
-        for prop in &self.0 {
+        // properties, in ascending attribute order
+        let mut props = self.0.iter().collect::<Vec<_>>();
+        props.sort_by_key(|prop| *prop.0);
+        for prop in props {
             data.extend(6_i16.to_be_bytes());
             data.extend(gds_record::PROPATTR);
             if *prop.0 < 1 || *prop.0 > 126 {
-                return Err(Box::new(gds_err!(&format!(
+                return Err(GdsError::ValueOutOfRange(format!(
                     "Gds property attribute value can not be out of range [1:126]:{:#?}",
                     &self
-                ))));
+                )));
             }
             data.extend(prop.0.to_be_bytes());
 
             let mut prop_value = Vec::<u8>::new();
             prop_value.extend(gds_record::PROPVALUE);
-            let mut value = gds_writer::ascii_string_to_be_bytes(&prop.1);
-            if value.len() % 2 != 0 {
+            let mut value = gds_writer::ascii_string_to_be_bytes(prop.1)?;
+            if !value.len().is_multiple_of(2) {
                 value.push(0);
             }
             if value.len() > 128 {
-                return Err(Box::new(gds_err!(&format!(
+                return Err(GdsError::ValueOutOfRange(format!(
                     "Gds property value can not have ascii char more than 128 count:{:#?}",
                     &self
-                ))));
+                )));
             }
             prop_value.extend(value);
 
@@ -0,0 +1,155 @@
+use super::*;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Gds record not decoded by gdsdk, like vendor records, written back as is
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawRecord {
+    /// record or item this record follows in its element, structure or lib
+    pub anchor: RawAnchor,
+    pub record_type: u8,
+    pub data_type: u8,
+    /// record data without length and type
+    pub bytes: Vec<u8>,
+}
+
+/// Record or item a raw record follows, raw record is written back after it even if
+/// elements or structures are written in different order than read
+///
+/// raw record whose anchor is not written, like removed element, is written before
+/// ENDEL, ENDSTR or ENDLIB
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RawAnchor {
+    /// after `occurrence`-th record of `record_type` in element or header of structure or lib,
+    /// counted from 0
+    Record { record_type: u8, occurrence: usize },
+    /// after element of structure, `index` is index in elements of its kind
+    Element { kind: ElementKind, index: usize },
+    /// after structure of lib
+    Struc(String),
+}
+
+impl GdsObject for RawRecord {
+    fn to_gds(&self, _: f64) -> Result<Vec<u8>, GdsError> {
+        let len = self.bytes.len() + 4;
+        if len > u16::MAX as usize || !len.is_multiple_of(2) {
+            return Err(GdsError::ValueOutOfRange(format!(
+                "Gds record length should be even and less than 65536: {}",
+                len
+            )));
+        }
+        let mut data = Vec::<u8>::with_capacity(len);
+        data.extend((len as u16).to_be_bytes());
+        data.push(self.record_type);
+        data.push(self.data_type);
+        data.extend(&self.bytes);
+        Ok(data)
+    }
+}
+
+// write records with raw records put after their anchors
+pub(crate) struct RawRecordWriter {
+    // raw records not written yet
+    raw_records: Vec<Option<RawRecord>>,
+    // indexes of raw records following anchor, in reading order
+    anchors: HashMap<RawAnchor, Vec<usize>>,
+    // count of written records of each record type
+    counts: HashMap<u8, usize>,
+}
+
+impl RawRecordWriter {
+    pub(crate) fn new(raw_records: &[RawRecord]) -> Self {
+        let mut anchors = HashMap::<RawAnchor, Vec<usize>>::new();
+        for (idx, raw) in raw_records.iter().enumerate() {
+            anchors.entry(raw.anchor.clone()).or_default().push(idx);
+        }
+        RawRecordWriter {
+            raw_records: raw_records.iter().cloned().map(Some).collect(),
+            anchors,
+            counts: HashMap::new(),
+        }
+    }
+
+    // write a record and raw records following it
+    pub(crate) fn record<W: Write>(
+        &mut self,
+        writer: &mut W,
+        record: &[u8],
+    ) -> Result<(), GdsError> {
+        writer.write_all(record)?;
+        if self.anchors.is_empty() {
+            return Ok(());
+        }
+        let record_type = record[2];
+        let count = self.counts.entry(record_type).or_default();
+        let anchor = RawAnchor::Record {
+            record_type,
+            occurrence: *count,
+        };
+        *count += 1;
+        self.after(writer, &anchor)
+    }
+
+    // write raw records following element or structure written by caller
+    pub(crate) fn after<W: Write>(
+        &mut self,
+        writer: &mut W,
+        anchor: &RawAnchor,
+    ) -> Result<(), GdsError> {
+        if let Some(indexes) = self.anchors.remove(anchor) {
+            for idx in indexes {
+                if let Some(raw) = self.raw_records[idx].take() {
+                    writer.write_all(&raw.to_gds(0.0)?)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // write raw records whose anchor is not written, then last record
+    pub(crate) fn last_record<W: Write>(
+        &mut self,
+        writer: &mut W,
+        record: &[u8],
+    ) -> Result<(), GdsError> {
+        for raw in self.raw_records.iter_mut().filter_map(Option::take) {
+            writer.write_all(&raw.to_gds(0.0)?)?;
+        }
+        self.anchors.clear();
+        writer.write_all(record)?;
+        Ok(())
+    }
+}
+
+// put raw records of element into its records written by to_gds
+pub(crate) fn with_raw_records(
+    data: Vec<u8>,
+    raw_records: &[RawRecord],
+) -> Result<Vec<u8>, GdsError> {
+    if raw_records.is_empty() {
+        return Ok(data);
+    }
+    let records = split_records(&data);
+    let mut raw_writer = RawRecordWriter::new(raw_records);
+    let mut out = Vec::<u8>::with_capacity(data.len());
+    for (idx, record) in records.iter().enumerate() {
+        if idx + 1 == records.len() {
+            raw_writer.last_record(&mut out, record)?;
+        } else {
+            raw_writer.record(&mut out, record)?;
+        }
+    }
+    Ok(out)
+}
+
+// split gds data written by to_gds into records
+pub(crate) fn split_records(data: &[u8]) -> Vec<&[u8]> {
+    let mut records = Vec::<&[u8]>::new();
+    let mut pos = 0;
+    while pos + 2 <= data.len() {
+        let len = u16::from_be_bytes([data[pos], data[pos + 1]]) as usize;
+        records.push(&data[pos..pos + len]);
+        pos += len;
+    }
+    records
+}
@@ -2,6 +2,7 @@ use std::sync::Arc;
 use std::sync::RwLock;
 
 use super::*;
+use crate::gds_error::ErrorContext;
 use crate::gds_record;
 use crate::gds_writer;
 
@@ -11,16 +12,24 @@ use crate::gds_writer;
 pub struct Ref {
     pub refed_struc: Arc<RwLock<Struc>>,
     pub reflection_x: bool,
-    // pub abs_magnific: bool,
+    /// magnification is not affected by magnification of parent
+    pub abs_magnification: bool,
     pub magnific: f64,
-    // pub abs_angel: bool,
-    pub angle: f64, //measured in degrees and in the counterclockwise direction
+    /// angle is not affected by angle of parent
+    pub abs_angle: bool,
+    pub angle: Angle,
     pub origin: Points,
     pub row: i16,
     pub column: i16,
     pub spaceing_row: Vector,
     pub spaceing_col: Vector,
     pub property: Property,
+    /// ELFLAGS record of element
+    pub elflags: Option<ElFlags>,
+    /// PLEX record of element
+    pub plex: Option<Plex>,
+    /// records not decoded, written back at their position in element
+    pub raw_records: Vec<RawRecord>,
 }
 
 impl Ref {
@@ -28,20 +37,25 @@ impl Ref {
         Ref {
             refed_struc: refto.clone(),
             reflection_x: false,
+            abs_magnification: false,
             magnific: 1.0,
-            angle: 0.0,
+            abs_angle: false,
+            angle: Angle::default(),
             origin: Points::new(0.0, 0.0),
             row: 0,
             column: 0,
             spaceing_row: Vector { x: 0.0, y: 0.0 },
             spaceing_col: Vector { x: 0.0, y: 0.0 },
             property: Property::default(),
+            elflags: None,
+            plex: None,
+            raw_records: Vec::new(),
         }
     }
 }
 
 impl GdsObject for Ref {
-    fn to_gds(&self, scaling: f64) -> Result<Vec<u8>, Box<dyn Error>> {
+    fn to_gds(&self, scaling: f64) -> Result<Vec<u8>, GdsError> {
         let mut data = Vec::<u8>::new();
 
         // sref or aref
@@ -57,13 +71,16 @@ impl GdsObject for Ref {
             data.extend(gds_record::SREF);
         }
 
+        // elflags and plex
+        data.extend(element_flags_to_gds(&self.elflags, &self.plex)?);
+
         // refered gds structure name
         let mut struc_name = Vec::<u8>::new();
         struc_name.extend(gds_record::SNAME);
 
         let struc = &*(self.refed_struc.read().unwrap());
-        let mut name = gds_writer::ascii_string_to_be_bytes(&struc.name);
-        if name.len() %2 != 0{
+        let mut name = gds_writer::ascii_string_to_be_bytes(&struc.name)?;
+        if !name.len().is_multiple_of(2) {
             name.push(0);
         }
         struc_name.extend(name);
@@ -79,17 +96,23 @@ impl GdsObject for Ref {
         if self.reflection_x {
             flag |= 0x8000
         }
+        if self.abs_magnification {
+            flag |= 0x0004
+        }
+        if self.abs_angle {
+            flag |= 0x0002
+        }
         data.extend(flag.to_be_bytes());
 
         // magnification
         data.extend(12_u16.to_be_bytes());
         data.extend(gds_record::MAG);
-        data.extend(gds_writer::f64_to_gds_bytes(self.magnific));
+        data.extend(gds_writer::f64_to_gds_bytes(self.magnific)?);
 
         // rotate
         data.extend(12_u16.to_be_bytes());
         data.extend(gds_record::ANGLE);
-        data.extend(gds_writer::f64_to_gds_bytes(self.angle));
+        data.extend(gds_writer::f64_to_gds_bytes(self.angle.degrees())?);
 
         if is_array {
             // colrow
@@ -137,7 +160,7 @@ impl GdsObject for Ref {
         data.extend(4_u16.to_be_bytes());
         data.extend(gds_record::ENDEL);
 
-        Ok(data)
+        with_raw_records(data, &self.raw_records)
     }
 }
 
@@ -145,16 +168,24 @@ impl GdsObject for Ref {
 pub(crate) struct FakeRef {
     pub refed_struc_name: String,
     pub reflection_x: bool,
-    // pub abs_magnific: bool,
+    pub abs_magnification: bool,
     pub magnific: f64,
-    // pub abs_angel: bool,
-    pub angle: f64, //measured in degrees and in the counterclockwise direction
+    pub abs_angle: bool,
+    pub angle: Angle,
     pub origin: Points,
     pub row: i16,
     pub column: i16,
     pub spaceing_row: Vector,
     pub spaceing_col: Vector,
     pub property: Property,
+    /// ELFLAGS record of element
+    pub elflags: Option<ElFlags>,
+    /// PLEX record of element
+    pub plex: Option<Plex>,
+    /// records not decoded, written back at their position in element
+    pub raw_records: Vec<RawRecord>,
+    // position of ref element in gds data
+    pub context: ErrorContext,
 }
 
 impl FakeRef {
@@ -162,21 +193,29 @@ impl FakeRef {
         FakeRef {
             refed_struc_name: String::new(),
             reflection_x: false,
+            abs_magnification: false,
             magnific: 1.0,
-            angle: 0.0,
+            abs_angle: false,
+            angle: Angle::default(),
             origin: Points::new(0.0, 0.0),
             row: 0,
             column: 0,
             spaceing_row: Vector { x: 0.0, y: 0.0 },
             spaceing_col: Vector { x: 0.0, y: 0.0 },
             property: Property::default(),
+            elflags: None,
+            plex: None,
+            raw_records: Vec::new(),
+            context: ErrorContext::default(),
         }
     }
 
     pub(crate) fn create_true_ref(self, struc: &Arc<RwLock<Struc>>) -> Ref {
         let mut struc_ref = Ref::new(struc);
         struc_ref.reflection_x = self.reflection_x;
+        struc_ref.abs_magnification = self.abs_magnification;
         struc_ref.magnific = self.magnific;
+        struc_ref.abs_angle = self.abs_angle;
         struc_ref.angle = self.angle;
         struc_ref.origin = self.origin;
         struc_ref.row = self.row;
@@ -184,6 +223,46 @@ impl FakeRef {
         struc_ref.spaceing_row = self.spaceing_row;
         struc_ref.spaceing_col = self.spaceing_col;
         struc_ref.property = self.property;
+        struc_ref.elflags = self.elflags;
+        struc_ref.plex = self.plex;
+        struc_ref.raw_records = self.raw_records;
         struc_ref
     }
 }
+
+#[cfg(test)]
+mod test_sref {
+    use super::*;
+    use crate::gds_model::test_gds_model::*;
+
+    #[test]
+    fn test_strans_abs_round_trip() {
+        let mut lib = Lib::new("test");
+        let top = Arc::new(RwLock::new(Struc::new("top")));
+        let cell = Arc::new(RwLock::new(Struc::new("cell")));
+        let mut sref = Ref::new(&cell);
+        sref.abs_magnification = true;
+        sref.abs_angle = true;
+        let text = Text {
+            text: "label".to_string(),
+            abs_angle: true,
+            ..Default::default()
+        };
+        top.write().unwrap().refs.push(sref);
+        top.write().unwrap().label.push(text);
+        lib.add_struc(&top).unwrap();
+        lib.add_struc(&cell).unwrap();
+        let (bytes, read_lib) = round_trip(&lib);
+        // STRANS of sref with absolute magnification and angle bits
+        assert!(bytes
+            .windows(6)
+            .any(|w| w == [0x00, 0x06, 0x1a, 0x01, 0x00, 0x06]));
+
+        let read_top = struc_of(&read_lib, "top");
+        let read_top = read_top.read().unwrap();
+        assert!(read_top.refs[0].abs_magnification);
+        assert!(read_top.refs[0].abs_angle);
+        assert!(!read_top.label[0].abs_magnification);
+        assert!(read_top.label[0].abs_angle);
+    }
+}
@@ -1,40 +1,89 @@
 use rayon::prelude::*;
+use std::io::Write;
 
 use super::*;
 use crate::gds_record;
 use crate::gds_writer;
+use crate::{Timestamps, WriteOptions};
 
 /// Gds Structure
 #[derive(Debug)]
 pub struct Struc {
     pub name: String,
+    /// STRCLASS bit array, written after STRNAME
+    pub strclass: Option<u16>,
     pub polygons: Vec<Polygon>,
     pub paths: Vec<Path>,
     pub refs: Vec<Ref>,
     pub label: Vec<Text>,
+    pub nodes: Vec<Node>,
+    pub boxes: Vec<GdsBox>,
     pub date: Date,
+    /// records not decoded, written back at their position in structure
+    pub raw_records: Vec<RawRecord>,
 }
 
 impl Struc {
     pub fn new(name: &str) -> Self {
         Struc {
             name: name.to_string(),
+            strclass: None,
             polygons: Vec::<Polygon>::new(),
             paths: Vec::<Path>::new(),
             refs: Vec::<Ref>::new(),
             label: Vec::<Text>::new(),
+            nodes: Vec::<Node>::new(),
+            boxes: Vec::<GdsBox>::new(),
             date: Date::now(),
+            raw_records: Vec::new(),
         }
     }
+
+    /// test bit of STRCLASS, bits are numbered from 0 as most significant bit like gds manual
+    pub fn strclass_bit(&self, bit: u8) -> bool {
+        bit < 16 && self.strclass.is_some_and(|c| c & (0x8000 >> bit) != 0)
+    }
+
+    /// set bit of STRCLASS, STRCLASS is created if not present, bit should be less than 16
+    pub fn set_strclass_bit(&mut self, bit: u8, value: bool) -> Result<(), GdsError> {
+        if bit >= 16 {
+            return Err(GdsError::ValueOutOfRange(format!(
+                "STRCLASS bit should be in range [0:15]: {}",
+                bit
+            )));
+        }
+        let class = self.strclass.get_or_insert(0);
+        if value {
+            *class |= 0x8000 >> bit;
+        } else {
+            *class &= !(0x8000 >> bit);
+        }
+        Ok(())
+    }
+
+    // check records of structure and its elements are supported by gds stream release
+    pub(crate) fn check_release(&self, release: i16) -> Result<(), GdsError> {
+        if self.strclass.is_some() {
+            check_record_release("STRCLASS", 5, release)?;
+        }
+        for element in self.elements() {
+            element.check_release(release)?;
+        }
+        Ok(())
+    }
 }
 
-impl GdsObject for Struc {
-    fn to_gds(&self, scaling: f64) -> Result<Vec<u8>, Box<dyn Error>> {
+// elements serialized in parallel at a time when writing
+const ELEMENT_CHUNK: usize = 1 << 12;
+
+impl Struc {
+    // BGNSTR, STRNAME and STRCLASS records
+    pub(crate) fn header_gds(&self, timestamps: &Timestamps) -> Result<Vec<u8>, GdsError> {
         let mut data = Vec::<u8>::new();
         // bgnstr and date
         let mut structure_data = Vec::<u8>::new();
         structure_data.extend(gds_record::BGNSTR);
-        structure_data.extend(self.date.to_gds(scaling)?);
+        structure_data.extend(timestamps.date(&self.date)?.to_gds(0.0)?);
 
         data.extend((structure_data.len() as i16 + 2_i16).to_be_bytes());
         data.extend(structure_data);
@@ -42,8 +91,8 @@ impl GdsObject for Struc {
         // gds struc name
         let mut struc_name = Vec::<u8>::new();
         struc_name.extend(gds_record::STRNAME);
-        let mut name = gds_writer::ascii_string_to_be_bytes(&self.name);
-        if name.len() % 2 != 0 {
+        let mut name = gds_writer::ascii_string_to_be_bytes(&self.name)?;
+        if !name.len().is_multiple_of(2) {
             name.push(0);
         }
         struc_name.extend(name);
@@ -51,49 +100,127 @@ impl GdsObject for Struc {
         data.extend((struc_name.len() as i16 + 2_i16).to_be_bytes());
         data.extend(struc_name);
 
-        for d in self
-            .polygons
-            .par_iter()
-            .map(|p| p.to_gds(scaling).unwrap())
-            .collect::<Vec<_>>()
-        {
-            data.extend(d);
+        // strclass
+        if let Some(strclass) = self.strclass {
+            data.extend(6_i16.to_be_bytes());
+            data.extend(gds_record::STRCLASS);
+            data.extend(strclass.to_be_bytes());
         }
+        Ok(data)
+    }
 
-        for d in self
-            .paths
-            .par_iter()
-            .map(|p| p.to_gds(scaling).unwrap())
-            .collect::<Vec<_>>()
-        {
-            data.extend(d);
+    /// write structure records to byte stream, elements are serialized chunk by chunk
+    pub(crate) fn write_to<W: Write>(
+        &self,
+        writer: &mut W,
+        scaling: f64,
+        options: &WriteOptions,
+    ) -> Result<(), GdsError> {
+        // raw records are put after structure header records and elements
+        let mut raw_writer = RawRecordWriter::new(&self.raw_records);
+        for record in split_records(&self.header_gds(&options.timestamps)?) {
+            raw_writer.record(writer, record)?;
         }
 
-        for d in self
-            .refs
-            .par_iter()
-            .map(|p| p.to_gds(scaling).unwrap())
-            .collect::<Vec<_>>()
-        {
-            data.extend(d);
-        }
+        write_elements(
+            ElementKind::Polygon,
+            &self.polygons,
+            writer,
+            &mut raw_writer,
+            |e| e.to_gds_limited(scaling, options),
+        )?;
+        write_elements(
+            ElementKind::Path,
+            &self.paths,
+            writer,
+            &mut raw_writer,
+            |e| e.to_gds_limited(scaling, options),
+        )?;
+        write_elements(ElementKind::Ref, &self.refs, writer, &mut raw_writer, |e| {
+            e.to_gds(scaling)
+        })?;
+        write_elements(
+            ElementKind::Text,
+            &self.label,
+            writer,
+            &mut raw_writer,
+            |e| e.to_gds(scaling),
+        )?;
+        write_elements(
+            ElementKind::Node,
+            &self.nodes,
+            writer,
+            &mut raw_writer,
+            |e| e.to_gds(scaling),
+        )?;
+        write_elements(
+            ElementKind::Box,
+            &self.boxes,
+            writer,
+            &mut raw_writer,
+            |e| e.to_gds(scaling),
+        )?;
+
+        // endstr
+        let mut endstr = Vec::<u8>::new();
+        endstr.extend(4_i16.to_be_bytes());
+        endstr.extend(gds_record::ENDSTR);
+        raw_writer.last_record(writer, &endstr)
+    }
+}
 
-        for d in self
-            .label
+// write elements of a kind, each followed by raw records anchored to it
+fn write_elements<T: Sync, W: Write>(
+    kind: ElementKind,
+    elements: &[T],
+    writer: &mut W,
+    raw_writer: &mut RawRecordWriter,
+    to_gds: impl Fn(&T) -> Result<Vec<u8>, GdsError> + Sync,
+) -> Result<(), GdsError> {
+    for (chunk_idx, chunk) in elements.chunks(ELEMENT_CHUNK).enumerate() {
+        let data = chunk
             .par_iter()
-            .map(|p| p.to_gds(scaling).unwrap())
-            .collect::<Vec<_>>()
-        {
-            data.extend(d);
+            .map(&to_gds)
+            .collect::<Result<Vec<_>, _>>()?;
+        for (idx, d) in data.into_iter().enumerate() {
+            writer.write_all(&d)?;
+            let index = chunk_idx * ELEMENT_CHUNK + idx;
+            raw_writer.after(writer, &RawAnchor::Element { kind, index })?;
         }
+    }
+    Ok(())
+}
 
-        // endstr
-        let mut endstr_data = Vec::<u8>::new();
-        endstr_data.extend(gds_record::ENDSTR);
+impl GdsObject for Struc {
+    fn to_gds(&self, scaling: f64) -> Result<Vec<u8>, GdsError> {
+        let mut data = Vec::<u8>::new();
+        self.write_to(&mut data, scaling, &WriteOptions::default())?;
+        Ok(data)
+    }
+}
 
-        data.extend((endstr_data.len() as i16 + 2_i16).to_be_bytes());
-        data.extend(endstr_data);
+#[cfg(test)]
+mod test_struc {
+    use super::*;
+    use crate::gds_model::test_gds_model::*;
 
-        Ok(data)
+    #[test]
+    fn test_strclass_srfname_round_trip() {
+        let (mut lib, _) = cell_lib(|s| {
+            s.set_strclass_bit(15, true).unwrap();
+            s.set_strclass_bit(13, true).unwrap();
+            s.set_strclass_bit(15, false).unwrap();
+        });
+        lib.srfname = Some("rules.srf".to_string());
+        let (_, read_lib) = round_trip(&lib);
+
+        assert_eq!(read_lib.srfname.as_deref(), Some("rules.srf"));
+        let read_struc = struc_of(&read_lib, "cell");
+        let read_struc = read_struc.read().unwrap();
+        assert_eq!(read_struc.strclass, Some(0x0004));
+        assert!(read_struc.strclass_bit(13));
+        assert!(!read_struc.strclass_bit(15));
+        assert!(!Struc::new("empty").strclass_bit(13));
+        assert!(Struc::new("empty").set_strclass_bit(16, true).is_err());
     }
 }
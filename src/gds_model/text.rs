@@ -2,12 +2,13 @@ use super::*;
 use crate::gds_record;
 use crate::gds_writer;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum TextAnchor {
     NW, // NorthWest
     N,
     NE, // NorthEast
     W,
+    #[default]
     O, // Origin
     E,
     SW, // SouthWest
@@ -15,27 +16,16 @@ pub enum TextAnchor {
     SE,
 }
 
-impl Default for TextAnchor {
-    fn default() -> TextAnchor {
-        TextAnchor::O
-    }
-}
-
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum TextFont {
+    #[default]
     Fonts0,
     Fonts1,
     Fonts2,
     Fonts3,
 }
 
-impl Default for TextFont {
-    fn default() -> Self {
-        TextFont::Fonts0
-    }
-}
-
-#[derive(Default, Debug)]
+#[derive(Debug)]
 pub struct Text {
     pub layer: i16,
     pub datatype: i16,
@@ -43,19 +33,61 @@ pub struct Text {
     pub text: String,
     pub position: Points,
     pub anchor: TextAnchor,
-    pub rotation: f64, // in radians
+    pub rotation: Angle,
     pub magnification: f64,
     pub x_reflection: bool, // reflection across the x axis.
+    /// magnification is not affected by magnification of parent
+    pub abs_magnification: bool,
+    /// rotation is not affected by rotation of parent
+    pub abs_angle: bool,
+    /// PATHTYPE record of text
+    pub path_type: Option<i16>,
+    /// WIDTH record of text, in user units, negative width is absolute
+    pub width: Option<f64>,
     pub repetition: Repetition,
     pub property: Property,
+    /// ELFLAGS record of element
+    pub elflags: Option<ElFlags>,
+    /// PLEX record of element
+    pub plex: Option<Plex>,
+    /// records not decoded, written back at their position in element
+    pub raw_records: Vec<RawRecord>,
+}
+
+impl Default for Text {
+    fn default() -> Self {
+        Text {
+            layer: 0,
+            datatype: 0,
+            font: TextFont::default(),
+            text: String::new(),
+            position: Points::default(),
+            anchor: TextAnchor::default(),
+            rotation: Angle::default(),
+            magnification: 1.0,
+            x_reflection: false,
+            abs_magnification: false,
+            abs_angle: false,
+            path_type: None,
+            width: None,
+            repetition: Repetition::default(),
+            property: Property::default(),
+            elflags: None,
+            plex: None,
+            raw_records: Vec::new(),
+        }
+    }
 }
 
 impl GdsObject for Text {
-    fn to_gds(&self, scaling: f64) -> Result<Vec<u8>, Box<dyn Error>> {
+    fn to_gds(&self, scaling: f64) -> Result<Vec<u8>, GdsError> {
         let mut data = Vec::<u8>::new();
 
         data.extend(4_i16.to_be_bytes());
         data.extend(gds_record::TEXT);
+
+        // elflags and plex
+        data.extend(element_flags_to_gds(&self.elflags, &self.plex)?);
         data.extend(6_i16.to_be_bytes());
         data.extend(gds_record::LAYER);
         data.extend((self.layer as u16).to_be_bytes());
@@ -64,24 +96,51 @@ impl GdsObject for Text {
         data.extend((self.datatype as u16).to_be_bytes());
         data.extend(6_u16.to_be_bytes());
         data.extend(gds_record::PRESENTATION);
-        data.extend((gds_writer::text_anchor_to_gds_num(&self.anchor) as u16).to_be_bytes());
+        let presentation = gds_writer::text_font_to_gds_num(&self.font) << 4
+            | gds_writer::text_anchor_to_gds_num(&self.anchor);
+        data.extend(presentation.to_be_bytes());
+
+        if let Some(path_type) = self.path_type {
+            data.extend(6_u16.to_be_bytes());
+            data.extend(gds_record::PATHTYPE);
+            data.extend(path_type.to_be_bytes());
+        }
+        if let Some(width) = self.width {
+            data.extend(8_u16.to_be_bytes());
+            data.extend(gds_record::WIDTH);
+            data.extend((f64::round(width * scaling) as i32).to_be_bytes());
+        }
 
-        let is_transform = self.rotation != 0.0 || self.magnification != 1.0 || self.x_reflection;
+        let is_transform = self.rotation.degrees() != 0.0
+            || self.magnification != 1.0
+            || self.x_reflection
+            || self.abs_magnification
+            || self.abs_angle;
 
         if is_transform {
             data.extend(6_u16.to_be_bytes());
             data.extend(gds_record::STRANS);
+            let mut flag: u16 = 0;
             if self.x_reflection {
-                data.extend((0x8000 as u16).to_be_bytes());
-            } else {
-                data.extend((0 as u16).to_be_bytes());
+                flag |= 0x8000
+            }
+            if self.abs_magnification {
+                flag |= 0x0004
+            }
+            if self.abs_angle {
+                flag |= 0x0002
+            }
+            data.extend(flag.to_be_bytes());
+            if self.magnification != 1.0 {
+                data.extend(12_u16.to_be_bytes());
+                data.extend(gds_record::MAG);
+                data.extend(gds_writer::f64_to_gds_bytes(self.magnification)?);
+            }
+            if self.rotation.degrees() != 0.0 {
+                data.extend(12_u16.to_be_bytes());
+                data.extend(gds_record::ANGLE);
+                data.extend(gds_writer::f64_to_gds_bytes(self.rotation.degrees())?);
             }
-            data.extend(12_u16.to_be_bytes());
-            data.extend(gds_record::MAG);
-            data.extend(gds_writer::f64_to_gds_bytes(self.magnification));
-            data.extend(12_u16.to_be_bytes());
-            data.extend(gds_record::ANGLE);
-            data.extend(gds_writer::f64_to_gds_bytes(self.rotation));
         }
         // XY
         data.extend(12_u16.to_be_bytes());
@@ -90,8 +149,8 @@ impl GdsObject for Text {
         data.extend((f64::round(self.position.y * scaling) as i32).to_be_bytes());
 
         // STRING
-        let mut text_data = gds_writer::ascii_string_to_be_bytes(&self.text);
-        if text_data.len() % 2 != 0 {
+        let mut text_data = gds_writer::ascii_string_to_be_bytes(&self.text)?;
+        if !text_data.len().is_multiple_of(2) {
             text_data.push(0);
         }
 
@@ -105,7 +164,7 @@ impl GdsObject for Text {
         data.extend(4_u16.to_be_bytes());
         data.extend(gds_record::ENDEL);
 
-        Ok(data)
+        with_raw_records(data, &self.raw_records)
     }
 }
 
@@ -116,3 +175,54 @@ pub struct Repetition {
     pub vec_1: Vector,
     pub vec_2: Vector,
 }
+
+#[cfg(test)]
+mod test_text {
+    use super::*;
+    use crate::gds_model::test_gds_model::*;
+
+    #[test]
+    fn test_text_round_trip() {
+        let text = Text {
+            layer: 4,
+            datatype: 5,
+            font: TextFont::Fonts2,
+            text: "pin".to_string(),
+            position: Points::new(1.0, 2.0),
+            anchor: TextAnchor::SE,
+            rotation: Angle::from_degrees(90.0),
+            magnification: 2.0,
+            path_type: Some(1),
+            width: Some(0.5),
+            ..Default::default()
+        };
+        let (lib, _) = cell_lib(|s| {
+            s.label.push(text);
+            s.label.push(Text::default());
+        });
+        let (bytes, read_lib) = round_trip(&lib);
+        // font 2 and south east anchor in PRESENTATION
+        assert!(bytes
+            .windows(6)
+            .any(|w| w == [0x00, 0x06, 0x17, 0x01, 0x00, 0x2a]));
+
+        let read_struc = struc_of(&read_lib, "cell");
+        let read_struc = read_struc.read().unwrap();
+        let text = &read_struc.label[0];
+        assert_eq!((text.layer, text.datatype), (4, 5));
+        assert!(matches!(text.font, TextFont::Fonts2));
+        assert!(matches!(text.anchor, TextAnchor::SE));
+        assert_eq!(text.rotation.degrees(), 90.0);
+        assert_eq!(text.magnification, 2.0);
+        assert_eq!(text.path_type, Some(1));
+        assert_eq!(text.width, Some(0.5));
+        assert_eq!((text.position.x, text.position.y), (1.0, 2.0));
+
+        // text without transform has no STRANS
+        assert_eq!(read_struc.label[1].magnification, 1.0);
+        let default_bytes = Text::default().to_gds(1000.0).unwrap();
+        assert!(!default_bytes
+            .windows(4)
+            .any(|w| w == [0x00, 0x06, 0x1a, 0x01]));
+    }
+}
@@ -0,0 +1,173 @@
+use super::*;
+
+/// Placement of structure or text, map local coords to coords of parent structure
+///
+/// point is reflected across x axis first, then magnified, rotated and moved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub dx: f64,
+    pub dy: f64,
+    pub angle: Angle,
+    pub magnification: f64,
+    pub x_reflection: bool,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform {
+            dx: 0.0,
+            dy: 0.0,
+            angle: Angle::default(),
+            magnification: 1.0,
+            x_reflection: false,
+        }
+    }
+}
+
+impl Transform {
+    pub fn apply(&self, point: &Points) -> Points {
+        let y = if self.x_reflection { -point.y } else { point.y };
+        let (sin, cos) = self.angle.radians().sin_cos();
+        let x = point.x * self.magnification;
+        let y = y * self.magnification;
+        Points::new(x * cos - y * sin + self.dx, x * sin + y * cos + self.dy)
+    }
+
+    /// transform of `child` placed in coords transformed by self
+    ///
+    /// absolute magnification or angle of child is not affected by self
+    pub fn compose(&self, child: &Transform, abs_magnification: bool, abs_angle: bool) -> Self {
+        let origin = self.apply(&Points::new(child.dx, child.dy));
+        let magnification = if abs_magnification {
+            child.magnification
+        } else {
+            self.magnification * child.magnification
+        };
+        let angle = if abs_angle {
+            child.angle
+        } else if self.x_reflection {
+            Angle::from_degrees(self.angle.degrees() - child.angle.degrees())
+        } else {
+            Angle::from_degrees(self.angle.degrees() + child.angle.degrees())
+        };
+        Transform {
+            dx: origin.x,
+            dy: origin.y,
+            angle,
+            magnification,
+            x_reflection: self.x_reflection != child.x_reflection,
+        }
+    }
+}
+
+impl Ref {
+    /// transform of first instance in coords of parent structure
+    pub fn transform(&self) -> Transform {
+        Transform {
+            dx: self.origin.x,
+            dy: self.origin.y,
+            angle: self.angle,
+            magnification: self.magnific,
+            x_reflection: self.reflection_x,
+        }
+    }
+
+    /// transforms of all instances placed in `parent`, absolute magnification and angle respected
+    pub fn instance_transforms(&self, parent: &Transform) -> Vec<Transform> {
+        let local = self.transform();
+        let columns = self.column.max(1);
+        let rows = self.row.max(1);
+        let mut transforms = Vec::with_capacity(columns as usize * rows as usize);
+        for col in 0..columns {
+            for row in 0..rows {
+                let instance = Transform {
+                    dx: local.dx
+                        + self.spaceing_col.x * col as f64
+                        + self.spaceing_row.x * row as f64,
+                    dy: local.dy
+                        + self.spaceing_col.y * col as f64
+                        + self.spaceing_row.y * row as f64,
+                    ..local
+                };
+                transforms.push(parent.compose(&instance, self.abs_magnification, self.abs_angle));
+            }
+        }
+        transforms
+    }
+}
+
+impl Text {
+    pub fn transform(&self) -> Transform {
+        Transform {
+            dx: self.position.x,
+            dy: self.position.y,
+            angle: self.rotation,
+            magnification: self.magnification,
+            x_reflection: self.x_reflection,
+        }
+    }
+
+    /// transform of text placed in `parent`, absolute magnification and angle respected
+    pub fn placed_transform(&self, parent: &Transform) -> Transform {
+        parent.compose(&self.transform(), self.abs_magnification, self.abs_angle)
+    }
+}
+
+#[cfg(test)]
+mod test_transform {
+    use super::*;
+    use float_cmp::{ApproxEq, F64Margin};
+    use std::sync::{Arc, RwLock};
+
+    fn approx(a: f64, b: f64) -> bool {
+        a.approx_eq(b, F64Margin::default())
+    }
+
+    #[test]
+    fn test_compose_absolute() {
+        let parent = Transform {
+            dx: 10.0,
+            dy: 0.0,
+            angle: Angle::from_degrees(90.0),
+            magnification: 2.0,
+            x_reflection: false,
+        };
+        let struc = Arc::new(RwLock::new(Struc::new("cell")));
+        let mut sref = Ref::new(&struc);
+        sref.origin = Points::new(1.0, 0.0);
+        sref.magnific = 3.0;
+        sref.angle = Angle::from_degrees(90.0);
+
+        let relative = sref.instance_transforms(&parent)[0];
+        assert!(approx(relative.dx, 10.0));
+        assert!(approx(relative.dy, 2.0));
+        assert!(approx(relative.magnification, 6.0));
+        assert_eq!(relative.angle.degrees(), 180.0);
+
+        sref.abs_magnification = true;
+        sref.abs_angle = true;
+        let absolute = sref.instance_transforms(&parent)[0];
+        assert!(approx(absolute.dx, 10.0));
+        assert!(approx(absolute.dy, 2.0));
+        assert!(approx(absolute.magnification, 3.0));
+        assert_eq!(absolute.angle.degrees(), 90.0);
+
+        let point = absolute.apply(&Points::new(1.0, 0.0));
+        assert!(approx(point.x, 10.0));
+        assert!(approx(point.y, 5.0));
+    }
+
+    #[test]
+    fn test_array_instances() {
+        let struc = Arc::new(RwLock::new(Struc::new("cell")));
+        let mut aref = Ref::new(&struc);
+        aref.column = 2;
+        aref.row = 3;
+        aref.spaceing_col = Vector::new(5.0, 0.0);
+        aref.spaceing_row = Vector::new(0.0, 4.0);
+        let transforms = aref.instance_transforms(&Transform::default());
+        assert_eq!(transforms.len(), 6);
+        assert!(approx(transforms[5].dx, 5.0));
+        assert!(approx(transforms[5].dy, 8.0));
+    }
+}
@@ -9,4 +9,4 @@ impl Vector {
     pub fn new(x: f64, y: f64) -> Self {
         Vector { x, y }
     }
-}
\ No newline at end of file
+}
@@ -1,189 +1,750 @@
-use super::gds_err;
-
 use super::gds_model;
 use super::gds_model::*;
 use super::gds_record::*;
 
+use crate::gds_diagnostic::{Diagnostic, Severity};
+use crate::gds_error::{ErrorContext, GdsError};
+use crate::gds_reader::ReadOptions;
 use rayon::prelude::*;
 use std::collections::HashMap;
-use std::slice::Iter;
-use std::error::Error;
-use std::sync::{Arc,RwLock};
+use std::sync::{Arc, RwLock};
 
-pub fn parse_gds(records: Vec<Record>) -> Result<Box<Lib>, Box<dyn Error+Send+Sync>> {
-    // first record should be gds header with version info
-    if let Some(Record::Header{version:ver}) =records.first() {
-        println!("read GDSII version: {}", ver);
+// parse structures in parallel once this many records are buffered
+const STRUC_BATCH_RECORDS: usize = 1 << 16;
+
+// gds structure parsed from records, references not connected yet
+pub(crate) struct ParsedStruc {
+    pub struc: Arc<RwLock<Struc>>,
+    pub refs: Vec<gds_model::FakeRef>,
+    // position of BGNSTR record
+    pub context: ErrorContext,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+// iterate records of a structure, remember last visited record for error context
+struct RecordCursor<'a> {
+    iter: std::slice::Iter<'a, PosRecord>,
+    last: Option<&'a PosRecord>,
+}
+
+impl<'a> RecordCursor<'a> {
+    fn new(records: &'a [PosRecord]) -> Self {
+        RecordCursor {
+            iter: records.iter(),
+            last: None,
+        }
+    }
+
+    // raw record of last visited record if it is not decoded
+    fn raw_record(
+        &self,
+        anchors: &mut RawAnchors,
+        reporter: &mut Reporter,
+    ) -> Result<Option<RawRecord>, GdsError> {
+        match self.last {
+            Some(r) => anchors.visit(r, reporter),
+            None => Ok(None),
+        }
+    }
+
+    fn peek(&self) -> Option<&'a Record> {
+        self.iter.clone().next().map(|r| &r.record)
+    }
+
+    fn error_context(&self) -> ErrorContext {
+        self.last.map(|r| r.error_context()).unwrap_or_default()
+    }
+}
+
+impl<'a> Iterator for RecordCursor<'a> {
+    type Item = &'a Record;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.last = Some(self.iter.next()?);
+        self.last.map(|r| &r.record)
+    }
+}
+
+// collect anomalies, anomaly is error in strict mode
+pub(crate) struct Reporter {
+    strict: bool,
+    // structure and element being parsed
+    scope: ErrorContext,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Reporter {
+    pub(crate) fn new(strict: bool) -> Self {
+        Reporter {
+            strict,
+            scope: ErrorContext::default(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, err: GdsError) -> Result<(), GdsError> {
+        if self.strict {
+            return Err(err);
+        }
+        self.diagnostics
+            .push(Diagnostic::from_error(Severity::Warning, &err));
+        Ok(())
+    }
+
+    fn info(&mut self, message: String, offset: u64) {
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Info,
+            offset,
+            message,
+        });
+    }
+
+    // report anomaly of record in current scope
+    fn report_at(&mut self, err: GdsError, record: ErrorContext) -> Result<(), GdsError> {
+        let context = self.context(record);
+        self.report(err.in_context(context))
     }
-    else {
-        return Err(Box::new(gds_err!("GDSII version not found")));
+
+    // record context in current scope
+    fn context(&self, record: ErrorContext) -> ErrorContext {
+        ErrorContext {
+            structure: self.scope.structure.clone(),
+            element: self.scope.element,
+            ..record
+        }
+    }
+}
+
+// anchors of raw records in element, structure or lib, a raw record follows last decoded
+// record, element or structure
+#[derive(Default)]
+pub(crate) struct RawAnchors {
+    // count of decoded records of each record type
+    counts: HashMap<u8, usize>,
+    last: Option<RawAnchor>,
+}
+
+impl RawAnchors {
+    // anchors of lib, after HEADER and BGNLIB
+    pub(crate) fn lib() -> Self {
+        let mut anchors = RawAnchors::default();
+        anchors.record(HEADER[0]);
+        anchors.record(BGNLIB[0]);
+        anchors
+    }
+
+    // decoded record is anchor of following raw records
+    pub(crate) fn record(&mut self, record_type: u8) {
+        let count = self.counts.entry(record_type).or_default();
+        self.last = Some(RawAnchor::Record {
+            record_type,
+            occurrence: *count,
+        });
+        *count += 1;
+    }
+
+    // element or structure is anchor of following raw records
+    pub(crate) fn item(&mut self, anchor: RawAnchor) {
+        self.last = Some(anchor);
+    }
+
+    // keep record not decoded after last anchor, or take decoded record as anchor,
+    // record type not defined by gds manual is an anomaly
+    pub(crate) fn visit(
+        &mut self,
+        pos_rec: &PosRecord,
+        reporter: &mut Reporter,
+    ) -> Result<Option<RawRecord>, GdsError> {
+        let Record::Unknown {
+            record_type,
+            data_type,
+            bytes,
+        } = &pos_rec.record
+        else {
+            self.record(pos_rec.record_type);
+            return Ok(None);
+        };
+        if record_name(*record_type).is_none() {
+            let err = GdsError::UnknownRecord {
+                offset: pos_rec.offset,
+                record_type: *record_type,
+                data_type: *data_type,
+            };
+            reporter.report_at(err, pos_rec.error_context())?;
+        }
+        Ok(Some(RawRecord {
+            anchor: self.last.clone().unwrap_or(RawAnchor::Record {
+                record_type: 0,
+                occurrence: 0,
+            }),
+            record_type: *record_type,
+            data_type: *data_type,
+            bytes: bytes.clone(),
+        }))
+    }
+}
+
+fn unexpected_record(record: &Record, scope: &str) -> GdsError {
+    GdsError::InvalidRecordData(format!("unexpected {} record in {}", record.name(), scope))
+}
+
+// checks records between BGNLIB and first BGNSTR follow order of gds stream syntax:
+// [SRFNAME] LIBNAME [REFLIBS] [FONTS] [ATTRTABLE] [GENERATIONS] [FORMAT {MASK}+ ENDMASKS] UNITS
+#[derive(Default)]
+pub(crate) struct LibRecordOrder {
+    last: u8,
+}
+
+impl LibRecordOrder {
+    const FORMAT: u8 = 7;
+    const MASK: u8 = 8;
+
+    pub(crate) fn check(&mut self, record: &Record) -> Result<(), GdsError> {
+        let rank = match record {
+            Record::SrfName(_) => 1,
+            Record::LibName(_) => 2,
+            Record::RefLibs(_) => 3,
+            Record::Fonts(_) => 4,
+            Record::AttrTable(_) => 5,
+            Record::Generations(_) => 6,
+            Record::Format(_) => Self::FORMAT,
+            Record::Mask(_) => Self::MASK,
+            Record::EndMasks => 9,
+            Record::Units { .. } => 10,
+            Record::BgnStr(_) => 11,
+            _ => return Ok(()),
+        };
+        let in_order = match record {
+            Record::Mask(_) => self.last == Self::FORMAT || self.last == Self::MASK,
+            Record::EndMasks => self.last == Self::MASK,
+            Record::BgnStr(_) => self.last != Self::MASK,
+            _ => rank > self.last && self.last != Self::MASK,
+        };
+        self.last = self.last.max(rank);
+        if in_order {
+            Ok(())
+        } else {
+            Err(GdsError::InvalidRecordData(format!(
+                "{} record out of order in library header",
+                record.name()
+            )))
+        }
     }
+}
+
+// parse gds lib, diagnostics found before an error are returned with the error too
+pub fn parse_gds<I>(
+    records: I,
+    options: &ReadOptions,
+) -> (Result<Box<Lib>, GdsError>, Vec<Diagnostic>)
+where
+    I: Iterator<Item = Result<PosRecord, GdsError>>,
+{
+    let mut reporter = Reporter::new(options.strict);
+    let lib = parse_records(records, &mut reporter);
+    let mut diagnostics = reporter.diagnostics;
+    diagnostics.sort_by_key(|d| d.offset);
+    (lib, diagnostics)
+}
+
+fn parse_records<I>(mut records: I, reporter: &mut Reporter) -> Result<Box<Lib>, GdsError>
+where
+    I: Iterator<Item = Result<PosRecord, GdsError>>,
+{
+    let lib = parse_lib_begin(&mut records, reporter)?;
+    parse_lib(records, lib, reporter)
+}
+
+// HEADER and BGNLIB records at beginning of gds data, shared with lazy reader
+pub(crate) fn parse_lib_begin<I>(
+    records: &mut I,
+    reporter: &mut Reporter,
+) -> Result<Box<Lib>, GdsError>
+where
+    I: Iterator<Item = Result<PosRecord, GdsError>>,
+{
+    // first record should be gds header with version info
+    let version = match records.next().transpose()? {
+        Some(
+            ref pos_rec @ PosRecord {
+                record: Record::Header { version: ver },
+                offset,
+                ..
+            },
+        ) => {
+            reporter.info(format!("read GDSII version: {}", ver), offset);
+            if let Err(e) = gds_release(ver) {
+                reporter.report(e.in_context(pos_rec.error_context()))?;
+            }
+            ver
+        }
+        Some(r) => {
+            return Err(
+                GdsError::InvalidRecordData("GDSII version not found".to_string())
+                    .in_context(r.error_context()),
+            )
+        }
+        None => {
+            return Err(GdsError::InvalidRecordData(
+                "not valid gds file, no any valid records found".to_string(),
+            ))
+        }
+    };
 
     // second record should be BgnLib, all data between BgnLib and EndLib is belong to this lib
     // which EndLib should be the last record
-    if let Some(Record::BgnLib(_)) = records.get(1){}
-    else {
-        return Err( Box::new(gds_err!("no valid gds lib found")));
+    let mut lib = Box::new(Lib::new(""));
+    lib.version = version;
+    match records.next().transpose()? {
+        Some(PosRecord {
+            record: Record::BgnLib(date),
+            ..
+        }) => lib.date = date, //modification time of lib, and marks beginning of library
+        Some(r) => {
+            return Err(
+                GdsError::InvalidRecordData("no valid gds lib found".to_string())
+                    .in_context(r.error_context()),
+            )
+        }
+        None => {
+            return Err(GdsError::InvalidRecordData(
+                "no valid gds lib found".to_string(),
+            ))
+        }
     }
-    if let Record::EndLib = records.last().unwrap(){}else{
-        return Err( Box::new(gds_err!("no valid gds lib found")));
+
+    Ok(lib)
+}
+
+// decode lib header record between BGNLIB and first BGNSTR into `lib`, shared with lazy
+// reader, returns false if record is not a lib header record
+//
+// `factor` is user units per database unit, set from UNITS record
+pub(crate) fn parse_lib_header(
+    lib: &mut Lib,
+    factor: &mut f64,
+    record: &Record,
+) -> Result<bool, GdsError> {
+    match record {
+        Record::SrfName(s) => lib.srfname = Some(s.clone()),
+        Record::LibName(s) => lib.name = s.clone(),
+        Record::RefLibs(names) => lib.reflibs = names.clone(),
+        Record::Fonts(fonts) => lib.fonts = fonts.clone().try_into().ok(),
+        Record::AttrTable(s) => lib.attrtable = Some(s.clone()),
+        Record::Generations(g) => lib.generations = Some(*g),
+        Record::Format(format_type) => {
+            lib.format = Some(LibFormat {
+                format_type: *format_type,
+                masks: Vec::new(),
+            })
+        }
+        Record::Mask(mask) => {
+            if let Some(format) = &mut lib.format {
+                format.masks.push(mask.clone());
+            }
+        }
+        Record::EndMasks => (),
+        Record::Units {
+            unit_in_meter,
+            precision,
+        } => {
+            let units = precision / unit_in_meter;
+            if !units.is_finite() {
+                return Err(GdsError::InvalidRecordData(format!(
+                    "Lib units is {}",
+                    units
+                )));
+            }
+            lib.units = units;
+            lib.precision = *precision;
+            *factor = *unit_in_meter;
+        }
+        _ => return Ok(false),
     }
-    
-    let lib = parse_lib(records)?;
-    return Ok(lib);
-    
+    Ok(true)
 }
 
-fn parse_lib(records: Vec<Record>) -> Result<Box<Lib>, Box<dyn Error+Send+Sync>> {
-    let mut lib = Box::new(Lib::new(""));
-    let mut factor = 0.0;
-    let mut strucs_id_range =Vec::<(Option<usize>, Option<usize>)>::new();
-    // 
-    for (idx, rec) in records.iter().enumerate() {
-        match rec {
-            Record::BgnLib(date) => lib.date = date.clone(), //modification time of lib, and marks beginning of library
-            Record::LibName(s) => lib.name = s.to_string(),
-            Record::Units {
-                unit_in_meter,
-                precision,
-            } => {
-                lib.units = precision / unit_in_meter;
-                if lib.units.is_infinite() {
-                    return Err(Box::new(gds_err!("Lib units is infinite")));
-                }
-                if lib.units.is_nan() {
-                    return Err(Box::new(gds_err!("Lib units is nan")));
-                }
+fn parse_lib<I>(
+    mut records: I,
+    mut lib: Box<Lib>,
+    reporter: &mut Reporter,
+) -> Result<Box<Lib>, GdsError>
+where
+    I: Iterator<Item = Result<PosRecord, GdsError>>,
+{
+    let mut factor = lib.precision / lib.units;
+    let mut strucs = Vec::<ParsedStruc>::new();
+    // records of gds structures waiting to be parsed
+    let mut batch = Vec::<Vec<PosRecord>>::new();
+    let mut batch_len = 0;
+    let mut end_lib = false;
+    // record read but not handled yet
+    let mut pending: Option<PosRecord> = None;
+    let mut order = LibRecordOrder::default();
+    let mut anchors = RawAnchors::lib();
 
-                lib.precision = *precision;
-                factor = *unit_in_meter;
+    loop {
+        let pos_rec = match pending.take() {
+            Some(r) => r,
+            None => match records.next() {
+                Some(Ok(r)) => r,
+                Some(Err(e)) => {
+                    reporter.report(e)?;
+                    continue;
+                }
+                None => break,
+            },
+        };
+        let context = pos_rec.error_context();
+        if let Err(e) = order.check(&pos_rec.record) {
+            reporter.report(e.in_context(context.clone()))?;
+        }
+        if let Some(raw) = anchors.visit(&pos_rec, reporter)? {
+            lib.raw_records.push(raw);
+            continue;
+        }
+        match parse_lib_header(&mut lib, &mut factor, &pos_rec.record) {
+            Ok(true) => continue,
+            Ok(false) => (),
+            Err(e) => {
+                reporter.report(e.in_context(context))?;
+                continue;
             }
-            // record gds structure start and end range
+        }
+        match pos_rec.record {
+            // collect all records of gds structure from BgnStr to EndStr
             Record::BgnStr(_) => {
-                strucs_id_range.push((None, None));
-                strucs_id_range.last_mut().unwrap().0 = Some(idx);
-            }
-            Record::EndStr => {  
-                strucs_id_range.last_mut().unwrap().1 = Some(idx); 
+                let struc_records;
+                (struc_records, pending) = collect_struc_records(pos_rec, &mut records, reporter)?;
+                if let Some(name) = struc_name(&struc_records) {
+                    anchors.item(RawAnchor::Struc(name));
+                }
+                batch_len += struc_records.len();
+                batch.push(struc_records);
+                if batch_len >= STRUC_BATCH_RECORDS {
+                    strucs.extend(parse_struc_batch(&batch, factor, reporter.strict)?);
+                    batch.clear();
+                    batch_len = 0;
+                }
             }
             Record::EndLib => {
+                end_lib = true;
                 break;
             }
-            _ => {}
+            ref other => {
+                reporter.report(unexpected_record(other, "library").in_context(context))?;
+            }
         }
     }
+    if !end_lib {
+        reporter.report(GdsError::InvalidRecordData(
+            "no valid gds lib found, ENDLIB record missing".to_string(),
+        ))?;
+    }
+    strucs.extend(parse_struc_batch(&batch, factor, reporter.strict)?);
 
-    // check gds structure range valid
-    // which means: 1. all range idx is Some(i64). 2. range not overlap
-    for i in 0..strucs_id_range.len() {
-        let (start, end) = strucs_id_range[i];
-        if start.is_none() || end.is_none() {
-            return Err(Box::new(gds_err!("Invalid gds structure range found")));
+    // step.1 save all parsed stuc to name_stuc_map, first struc wins if name duplicated
+    let mut name_struc_map = HashMap::<String, Arc<RwLock<Struc>>>::new();
+    let mut struc_ref_strucname_map = HashMap::<String, Vec<gds_model::FakeRef>>::new();
+    let mut struc_names = Vec::<(String, ErrorContext)>::new();
+    for parsed in strucs {
+        reporter.diagnostics.extend(parsed.diagnostics);
+        let struc_name = parsed.struc.read().unwrap().name.clone();
+        if name_struc_map.contains_key(&struc_name) {
+            reporter.report(GdsError::DuplicateStructure(struc_name).in_context(parsed.context))?;
+            continue;
         }
+        name_struc_map.insert(struc_name.clone(), parsed.struc);
+        struc_ref_strucname_map.insert(struc_name.clone(), parsed.refs);
+        struc_names.push((struc_name, parsed.context));
+    }
 
-        if i>0 {
-             if let (Some(pre_start), Some(pre_end)) = strucs_id_range[i-1]{
-                if pre_start.max(start.unwrap()) < pre_end.min(end.unwrap()) {
-                    return Err(Box::new(gds_err!(
-                        "Gds structure range overlap"
-                    )));
+    // step.2 connect reference to struc
+    let undefined_refs = struc_ref_strucname_map
+        .par_drain()
+        .map(|(struc_name, fack_refs)| {
+            let cur_struc = name_struc_map.get(&struc_name).unwrap().clone();
+            let mut mut_cur_struc = cur_struc.write().unwrap();
+            let mut undefined_refs = Vec::new();
+            // index of parsed ref in refs of struc, undefined ref is replaced by previous ref,
+            // none if no previous ref is kept
+            let mut ref_indexes = Vec::with_capacity(fack_refs.len());
+            for fack_ref in fack_refs {
+                match name_struc_map.get(&fack_ref.refed_struc_name) {
+                    Some(ref_struc) => mut_cur_struc.refs.push(fack_ref.create_true_ref(ref_struc)),
+                    None => undefined_refs.push(
+                        GdsError::UndefinedReference(fack_ref.refed_struc_name)
+                            .in_context(fack_ref.context),
+                    ),
                 }
-             }   
-        }
-    }
-
-    
-   // step.1 parse all stuc, save to name_stuc_map
-    let name_struc_map = Arc::new(RwLock::new( HashMap::<String, Arc<RwLock<Struc>>>::new()));
-    let struc_ref_strucname_map =Arc::new(RwLock::new(HashMap::<String, Vec::<gds_model::FakeRef>>::new()));
-   
-
-    strucs_id_range.par_iter().for_each(|(start, end)|{
-        let mut write_name_struc_map = name_struc_map.write().unwrap();
-        let mut write_struc_ref_strucname_map = struc_ref_strucname_map.write().unwrap();
-        let mut iter = records[start.unwrap() as usize..end.unwrap() as usize].iter();
-        let (struc, fack_refs) = parse_struc( &mut iter, factor).unwrap();
-        let struc_name = struc.read().unwrap().name.clone();
-        if write_name_struc_map.contains_key(&struc_name) {
-                           return;
-                        }
-        write_name_struc_map.insert(struc_name.clone(), struc);
-        write_struc_ref_strucname_map.insert(struc_name, fack_refs);
-    });
-    
+                ref_indexes.push(mut_cur_struc.refs.len().checked_sub(1));
+            }
+            if !undefined_refs.is_empty() {
+                // raw records without previous ref follow header of structure
+                let header = RawAnchor::Record {
+                    record_type: match mut_cur_struc.strclass {
+                        Some(_) => STRCLASS[0],
+                        None => STRNAME[0],
+                    },
+                    occurrence: 0,
+                };
+                for raw in &mut mut_cur_struc.raw_records {
+                    if let RawAnchor::Element {
+                        kind: ElementKind::Ref,
+                        index,
+                    } = raw.anchor
+                    {
+                        raw.anchor = match ref_indexes[index] {
+                            Some(index) => RawAnchor::Element {
+                                kind: ElementKind::Ref,
+                                index,
+                            },
+                            None => header.clone(),
+                        };
+                    }
+                }
+            }
+            undefined_refs
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+    for err in undefined_refs {
+        reporter.report(err)?;
+    }
 
-    // step.2 connect reference to struc
-    let mut write_struc_ref_strucname_map = struc_ref_strucname_map.write().unwrap();
-    let read_name_struc_map = name_struc_map.read().unwrap();
-    write_struc_ref_strucname_map.par_drain().for_each(|(struc_name, fack_refs)|{
-        let cur_struc = read_name_struc_map.get(&struc_name).unwrap().clone();
-        let mut mut_cur_struc = cur_struc.write().unwrap();
-        for fack_ref in fack_refs{
-            let ref_struc = read_name_struc_map.get(&fack_ref.refed_struc_name).unwrap().clone();
-            let struc_ref = fack_ref.create_true_ref(&ref_struc);
-            mut_cur_struc.refs.push(struc_ref);
+    // step.3 add all struc to lib in file order
+    for (name, context) in struc_names {
+        if let Err(e) = lib.add_struc(&name_struc_map[&name]) {
+            reporter.report(e.in_context(context))?;
         }
-    });
-
-    // step.3 add all struc to lib
-    let read_name_struc_map = name_struc_map.read().unwrap();
-    for c in read_name_struc_map.iter(){
-        lib.add_struc(&c.1)?;
     }
 
     Ok(lib)
 }
 
-fn parse_struc(
-    iter: &mut Iter<'_, Record>,
-    factor: f64
-) -> Result<(Arc<RwLock<Struc>>,Vec::<gds_model::FakeRef>), Box<dyn Error+Send+Sync>> {
+// collect records of a gds structure, from BgnStr to EndStr
+//
+// return records and a pending BGNSTR or ENDLIB record found before ENDSTR in non-strict mode
+fn collect_struc_records<I>(
+    bgn_str: PosRecord,
+    records: &mut I,
+    reporter: &mut Reporter,
+) -> Result<(Vec<PosRecord>, Option<PosRecord>), GdsError>
+where
+    I: Iterator<Item = Result<PosRecord, GdsError>>,
+{
+    // track structure and element for context of reading error
+    let mut context = bgn_str.error_context();
+    let mut element_count = 0;
+    let mut struc_records = vec![bgn_str];
+    loop {
+        let pos_rec = match records.next() {
+            Some(Ok(r)) => r,
+            Some(Err(e)) => {
+                reporter.report(e.in_context(context.clone()))?;
+                continue;
+            }
+            None => {
+                reporter.report(
+                    GdsError::InvalidRecordData("Invalid gds structure range found".to_string())
+                        .in_context(context),
+                )?;
+                return Ok((struc_records, None));
+            }
+        };
+        match &pos_rec.record {
+            Record::StrName(name) => context.structure = Some(name.clone()),
+            Record::Boundary
+            | Record::Path
+            | Record::StrRef
+            | Record::AryRef
+            | Record::Text
+            | Record::Box
+            | Record::Node => {
+                context.element = Some((pos_rec.record.name(), element_count));
+                element_count += 1;
+            }
+            Record::EndElem => context.element = None,
+            Record::BgnStr(_) | Record::EndLib => {
+                reporter.report(
+                    GdsError::InvalidRecordData("Gds structure range overlap".to_string())
+                        .in_context(pos_rec.error_context())
+                        .in_context(context),
+                )?;
+                return Ok((struc_records, Some(pos_rec)));
+            }
+            Record::EndStr => {
+                struc_records.push(pos_rec);
+                return Ok((struc_records, None));
+            }
+            _ => {}
+        }
+        struc_records.push(pos_rec);
+    }
+}
+
+// name in STRNAME record of structure records
+pub(crate) fn struc_name(records: &[PosRecord]) -> Option<String> {
+    records.iter().find_map(|r| match &r.record {
+        Record::StrName(name) => Some(name.clone()),
+        _ => None,
+    })
+}
+
+fn parse_struc_batch(
+    batch: &[Vec<PosRecord>],
+    factor: f64,
+    strict: bool,
+) -> Result<Vec<ParsedStruc>, GdsError> {
+    batch
+        .par_iter()
+        .map(|struc_records| parse_struc(struc_records, factor, strict))
+        .collect()
+}
+
+pub(crate) fn parse_struc(
+    records: &[PosRecord],
+    factor: f64,
+    strict: bool,
+) -> Result<ParsedStruc, GdsError> {
     let struc_ptr = Arc::new(RwLock::new(Struc::new("")));
     let mut ref_refname = Vec::<gds_model::FakeRef>::new();
     let mut struc = struc_ptr.write().unwrap();
+    let mut reporter = Reporter::new(strict);
+    let mut iter = RecordCursor::new(records);
+    let mut struc_context = ErrorContext::default();
+    let mut element_count = 0;
+    let mut anchors = RawAnchors::default();
     while let Some(record) = iter.next() {
-        match record {
-            Record::BgnStr(date) => struc.date = date.clone(), // last modification time of a structure and marks the beginning of a structure
-            Record::StrName(s) => struc.name = s.to_string(),
-            Record::Boundary | Record::Box => {
-                let polygon = parse_polygon(iter, factor)?;
-                struc.polygons.push(polygon);
-            }
-            Record::Path => {
-                let path = parse_path(iter, factor)?;
-                struc.paths.push(path);
-            }
-            Record::StrRef => {
-                let sref = parse_sref(iter, factor)?;
-                ref_refname.push(sref);
+        if let Some(raw) = iter.raw_record(&mut anchors, &mut reporter)? {
+            struc.raw_records.push(raw);
+            continue;
+        }
+        reporter.scope.element = Some((record.name(), element_count));
+        let element_context = reporter.context(iter.error_context());
+        let parsed = match record {
+            Record::BgnStr(date) => {
+                struc.date = date.clone(); // last modification time of a structure and marks the beginning of a structure
+                struc_context = iter.error_context();
+                continue;
             }
-            Record::Text => {
-                let text = parse_text(iter, factor)?;
-                struc.label.push(text)
+            Record::StrName(s) => {
+                struc.name = s.to_string();
+                reporter.scope.structure = Some(s.to_string());
+                struc_context.structure = Some(s.to_string());
+                continue;
             }
-            Record::AryRef => {
-                let aref  = parse_aref(iter, factor)?;
-                ref_refname.push(aref );
+            Record::StrClass(c) => {
+                struc.strclass = Some(*c);
+                continue;
             }
+            Record::Boundary => parse_polygon(&mut iter, factor, &mut reporter).map(|polygon| {
+                struc.polygons.push(polygon);
+                anchors.item(RawAnchor::Element {
+                    kind: ElementKind::Polygon,
+                    index: struc.polygons.len() - 1,
+                });
+            }),
+            Record::Box => parse_box(&mut iter, factor, &mut reporter).map(|gds_box| {
+                struc.boxes.push(gds_box);
+                anchors.item(RawAnchor::Element {
+                    kind: ElementKind::Box,
+                    index: struc.boxes.len() - 1,
+                });
+            }),
+            Record::Path => parse_path(&mut iter, factor, &mut reporter).map(|path| {
+                struc.paths.push(path);
+                anchors.item(RawAnchor::Element {
+                    kind: ElementKind::Path,
+                    index: struc.paths.len() - 1,
+                });
+            }),
+            Record::StrRef => parse_sref(&mut iter, factor, &mut reporter).map(|mut sref| {
+                sref.context = element_context;
+                ref_refname.push(sref);
+                anchors.item(RawAnchor::Element {
+                    kind: ElementKind::Ref,
+                    index: ref_refname.len() - 1,
+                });
+            }),
+            Record::Text => parse_text(&mut iter, factor, &mut reporter).map(|text| {
+                struc.label.push(text);
+                anchors.item(RawAnchor::Element {
+                    kind: ElementKind::Text,
+                    index: struc.label.len() - 1,
+                });
+            }),
+            Record::AryRef => parse_aref(&mut iter, factor, &mut reporter).map(|mut aref| {
+                aref.context = element_context;
+                ref_refname.push(aref);
+                anchors.item(RawAnchor::Element {
+                    kind: ElementKind::Ref,
+                    index: ref_refname.len() - 1,
+                });
+            }),
+            Record::Node => parse_node(&mut iter, factor, &mut reporter).map(|node| {
+                struc.nodes.push(node);
+                anchors.item(RawAnchor::Element {
+                    kind: ElementKind::Node,
+                    index: struc.nodes.len() - 1,
+                });
+            }),
             Record::EndStr => {
                 break;
             }
             other => {
-                println!("get record from struc {:#?}", other);
+                reporter.scope.element = None;
+                reporter.report_at(unexpected_record(other, "structure"), iter.error_context())?;
+                continue;
             }
+        };
+        element_count += 1;
+        if let Err(e) = parsed {
+            // skip broken element in non-strict mode
+            reporter.report_at(e, iter.error_context())?;
+            skip_element(&mut iter);
         }
     }
     drop(struc);
 
-    Ok((struc_ptr, ref_refname))
+    Ok(ParsedStruc {
+        struc: struc_ptr,
+        refs: ref_refname,
+        context: struc_context,
+        diagnostics: reporter.diagnostics,
+    })
+}
+
+// skip rest records of element, stop after ENDEL or before ENDSTR
+fn skip_element(iter: &mut RecordCursor<'_>) {
+    while let Some(record) = iter.peek() {
+        if let Record::EndStr = record {
+            break;
+        }
+        iter.next();
+        if let Record::EndElem = record {
+            break;
+        }
+    }
 }
 
-fn parse_text(iter: &mut Iter<'_, Record>, factor: f64) -> Result<Text, Box<dyn Error+Send+Sync>> {
+fn parse_text(
+    iter: &mut RecordCursor<'_>,
+    factor: f64,
+    reporter: &mut Reporter,
+) -> Result<Text, GdsError> {
     let mut text = Text::default();
-    let mut cur_prokey : Option<i16>= None;
-    while let Some(record) = iter.next() {
+    let mut anchors = RawAnchors::default();
+    let mut cur_prokey: Option<i16> = None;
+    while let Some(record) = next_element_record(iter) {
+        if let Some(raw) = iter.raw_record(&mut anchors, reporter)? {
+            text.raw_records.push(raw);
+            continue;
+        }
         match record {
             Record::Text => (), //marks the beginning of a text element
+            Record::ElFlags(f) => text.elflags = Some(ElFlags(*f)),
+            Record::Plex(p) => text.plex = Some(Plex(*p)),
             Record::Layer(l) => text.layer = *l,
             Record::TextType(d) => text.datatype = *d,
             Record::Presentation {
@@ -215,198 +776,802 @@ fn parse_text(iter: &mut Iter<'_, Record>, factor: f64) -> Result<Text, Box<dyn
                     },
                 }
             }
+            Record::PathType(t) => text.path_type = Some(*t),
+            Record::Width(w) => text.width = Some(*w as f64 * factor),
             Record::String(content) => text.text = content.clone(),
             Record::Mag(mag) => text.magnification = *mag,
-            Record::Angle(angle) => text.rotation = std::f64::consts::PI / 180.0 * angle,
-            // TODO:
+            Record::Angle(angle) => text.rotation = Angle::from_degrees(*angle),
             Record::RefTrans {
                 reflection_x,
-                ..
-                // absolute_magnification,
-                // absolute_angle,
-            } => text.x_reflection = *reflection_x,
-            Record::Points(points) => {
-                text.position =
-                    Points::new(points[0].0 as f64 * factor, points[0].1 as f64 * factor)
+                absolute_magnification,
+                absolute_angle,
+            } => {
+                text.x_reflection = *reflection_x;
+                text.abs_magnification = *absolute_magnification;
+                text.abs_angle = *absolute_angle;
             }
-            Record::PropAttr(key)=>cur_prokey = Some(*key),
+            Record::Points(points) => text.position = first_point(points, factor)?,
+            Record::PropAttr(key) => cur_prokey = Some(*key),
             Record::PropValue(value) => {
-                if let Some(key) = cur_prokey{
-                    text.property.0.insert(key, value.to_string());   
-                }else{
-                    return Err(Box::new(gds_err!(&std::format!(
+                if let Some(key) = cur_prokey {
+                    text.property.0.insert(key, value.to_string());
+                } else {
+                    return Err(GdsError::InvalidRecordData(format!(
                         "Text Property value \"{}\" have no key",
-                        &value))));
+                        value
+                    )));
                 }
             }
-            Record::EndElem => break,
-            other => {
-                println!("get record from Text {:#?}", other);
-            }
+            Record::EndElem => return Ok(text),
+            other => reporter.report_at(
+                unexpected_record(other, "TEXT element"),
+                iter.error_context(),
+            )?,
         }
     }
+    reporter.report_at(missing_endel(), iter.error_context())?;
     Ok(text)
 }
 
-fn parse_polygon(iter: &mut Iter<'_, Record>, factor: f64) -> Result<Polygon, Box<dyn Error+Send+Sync>> {
+fn parse_polygon(
+    iter: &mut RecordCursor<'_>,
+    factor: f64,
+    reporter: &mut Reporter,
+) -> Result<Polygon, GdsError> {
     let mut polygon = Polygon::default();
-    let mut cur_prokey : Option<i16>= None;
-    while let Some(record) = iter.next() {
+    let mut anchors = RawAnchors::default();
+    let mut cur_prokey: Option<i16> = None;
+    while let Some(record) = next_element_record(iter) {
+        if let Some(raw) = iter.raw_record(&mut anchors, reporter)? {
+            polygon.raw_records.push(raw);
+            continue;
+        }
         match record {
             Record::Boundary => (), //marks the beginning of a boundary element
+            Record::ElFlags(f) => polygon.elflags = Some(ElFlags(*f)),
+            Record::Plex(p) => polygon.plex = Some(Plex(*p)),
             Record::Layer(l) => polygon.layer = *l,
-            Record::DataType(d) | Record::BoxType(d)=> polygon.datatype = *d,
+            Record::DataType(d) => polygon.datatype = *d,
             Record::Points(points) => {
                 if let Some((_, elements)) = points.split_last() {
                     // gds polygon last points is same with first one, so slice it
                     polygon.points = i32_vec_2_pointvec(elements, factor);
-                }                
+                }
             }
-            Record::PropAttr(key)=>cur_prokey = Some(*key),
+            Record::PropAttr(key) => cur_prokey = Some(*key),
             Record::PropValue(value) => {
-                if let Some(key) = cur_prokey{
-                    polygon.property.0.insert(key, value.to_string());   
-                }else{
-                    return Err(Box::new(gds_err!(&std::format!(
+                if let Some(key) = cur_prokey {
+                    polygon.property.0.insert(key, value.to_string());
+                } else {
+                    return Err(GdsError::InvalidRecordData(format!(
                         "Polygon Property value \"{}\" have no key",
-                        &value))));
+                        value
+                    )));
                 }
             }
-            Record::EndElem => break,
-            other => {
-                println!("get record from polygon {:#?}", other);
-            }
+            Record::EndElem => return Ok(polygon),
+            other => reporter.report_at(
+                unexpected_record(other, "BOUNDARY element"),
+                iter.error_context(),
+            )?,
         }
     }
+    reporter.report_at(missing_endel(), iter.error_context())?;
     Ok(polygon)
 }
 
-fn parse_path(iter: &mut Iter<'_, Record>, factor: f64) -> Result<Path, Box<dyn Error+Send+Sync>> {
+fn parse_box(
+    iter: &mut RecordCursor<'_>,
+    factor: f64,
+    reporter: &mut Reporter,
+) -> Result<GdsBox, GdsError> {
+    let mut gds_box = GdsBox::default();
+    let mut anchors = RawAnchors::default();
+    let mut cur_prokey: Option<i16> = None;
+    while let Some(record) = next_element_record(iter) {
+        if let Some(raw) = iter.raw_record(&mut anchors, reporter)? {
+            gds_box.raw_records.push(raw);
+            continue;
+        }
+        match record {
+            Record::Box => (), // marks the beginning of a box element
+            Record::ElFlags(f) => gds_box.elflags = Some(ElFlags(*f)),
+            Record::Plex(p) => gds_box.plex = Some(Plex(*p)),
+            Record::Layer(l) => gds_box.layer = *l,
+            Record::BoxType(t) => gds_box.boxtype = *t,
+            Record::Points(points) => {
+                if let Some((_, elements)) = points.split_last() {
+                    // gds box last points is same with first one, so slice it
+                    gds_box.points = i32_vec_2_pointvec(elements, factor);
+                }
+            }
+            Record::PropAttr(key) => cur_prokey = Some(*key),
+            Record::PropValue(value) => {
+                if let Some(key) = cur_prokey {
+                    gds_box.property.0.insert(key, value.to_string());
+                } else {
+                    return Err(GdsError::InvalidRecordData(format!(
+                        "Box Property value \"{}\" have no key",
+                        value
+                    )));
+                }
+            }
+            Record::EndElem => return Ok(gds_box),
+            other => reporter.report_at(
+                unexpected_record(other, "BOX element"),
+                iter.error_context(),
+            )?,
+        }
+    }
+    reporter.report_at(missing_endel(), iter.error_context())?;
+    Ok(gds_box)
+}
+
+fn parse_node(
+    iter: &mut RecordCursor<'_>,
+    factor: f64,
+    reporter: &mut Reporter,
+) -> Result<Node, GdsError> {
+    let mut node = Node::default();
+    let mut anchors = RawAnchors::default();
+    let mut cur_prokey: Option<i16> = None;
+    while let Some(record) = next_element_record(iter) {
+        if let Some(raw) = iter.raw_record(&mut anchors, reporter)? {
+            node.raw_records.push(raw);
+            continue;
+        }
+        match record {
+            Record::Node => (), // marks the beginning of a node element
+            Record::ElFlags(f) => node.elflags = Some(ElFlags(*f)),
+            Record::Plex(p) => node.plex = Some(Plex(*p)),
+            Record::Layer(l) => node.layer = *l,
+            Record::NodeType(t) => node.nodetype = *t,
+            Record::Points(points) => node.points = i32_vec_2_pointvec(points, factor),
+            Record::PropAttr(key) => cur_prokey = Some(*key),
+            Record::PropValue(value) => {
+                if let Some(key) = cur_prokey {
+                    node.property.0.insert(key, value.to_string());
+                } else {
+                    return Err(GdsError::InvalidRecordData(format!(
+                        "Node Property value \"{}\" have no key",
+                        value
+                    )));
+                }
+            }
+            Record::EndElem => return Ok(node),
+            other => reporter.report_at(
+                unexpected_record(other, "NODE element"),
+                iter.error_context(),
+            )?,
+        }
+    }
+    reporter.report_at(missing_endel(), iter.error_context())?;
+    Ok(node)
+}
+
+fn parse_path(
+    iter: &mut RecordCursor<'_>,
+    factor: f64,
+    reporter: &mut Reporter,
+) -> Result<Path, GdsError> {
     let mut path = Path::default();
-    let mut cur_prokey : Option<i16>= None;
-    while let Some(record) = iter.next() {
+    let mut anchors = RawAnchors::default();
+    let mut cur_prokey: Option<i16> = None;
+    while let Some(record) = next_element_record(iter) {
+        if let Some(raw) = iter.raw_record(&mut anchors, reporter)? {
+            path.raw_records.push(raw);
+            continue;
+        }
         match record {
             Record::Path => (), // marks the beginning of a path element
+            Record::ElFlags(f) => path.elflags = Some(ElFlags(*f)),
+            Record::Plex(p) => path.plex = Some(Plex(*p)),
             Record::Layer(l) => path.layer = *l,
             Record::DataType(d) => path.datatype = *d,
             Record::Width(w) => path.width = *w as f64 * factor,
             Record::PathType(t) => path.end_type = t.try_into()?,
+            Record::BgnExtn(e) => path.extend_begin = *e as f64 * factor,
+            Record::EndExtn(e) => path.extend_end = *e as f64 * factor,
             Record::Points(points) => {
                 path.points = i32_vec_2_pointvec(points, factor);
             }
-            Record::PropAttr(key)=>cur_prokey = Some(*key),
+            Record::PropAttr(key) => cur_prokey = Some(*key),
             Record::PropValue(value) => {
-                if let Some(key) = cur_prokey{
-                    path.property.0.insert(key, value.to_string());   
-                }else{
-                    return Err(Box::new(gds_err!(&std::format!(
+                if let Some(key) = cur_prokey {
+                    path.property.0.insert(key, value.to_string());
+                } else {
+                    return Err(GdsError::InvalidRecordData(format!(
                         "Path Property value \"{}\" have no key",
-                        &value))));
+                        value
+                    )));
                 }
             }
-            Record::EndElem => break,
-            other => {
-                println!("get record from path {:#?}", other);
-            }
+            Record::EndElem => return Ok(path),
+            other => reporter.report_at(
+                unexpected_record(other, "PATH element"),
+                iter.error_context(),
+            )?,
         }
     }
+    reporter.report_at(missing_endel(), iter.error_context())?;
     Ok(path)
 }
 
-fn parse_sref(iter: &mut Iter<'_, Record>, factor: f64) -> Result<FakeRef, Box<dyn Error+Send+Sync>> {
+fn parse_sref(
+    iter: &mut RecordCursor<'_>,
+    factor: f64,
+    reporter: &mut Reporter,
+) -> Result<FakeRef, GdsError> {
     let mut sref = FakeRef::new();
-    let mut cur_prokey : Option<i16>= None;
-    while let Some(record) = iter.next() {
+    let mut anchors = RawAnchors::default();
+    let mut cur_prokey: Option<i16> = None;
+    while let Some(record) = next_element_record(iter) {
+        if let Some(raw) = iter.raw_record(&mut anchors, reporter)? {
+            sref.raw_records.push(raw);
+            continue;
+        }
         match record {
             Record::StrRef => (), // marks the beginning of an SREF(structure reference) element
+            Record::ElFlags(f) => sref.elflags = Some(ElFlags(*f)),
+            Record::Plex(p) => sref.plex = Some(Plex(*p)),
             Record::StrRefName(s) => sref.refed_struc_name = s.to_string(),
             Record::RefTrans {
                 reflection_x,
-                ..
-                // absolute_magnification,
-                // absolute_angle,
+                absolute_magnification,
+                absolute_angle,
             } => {
                 sref.reflection_x = *reflection_x;
-                // sref.abs_magnific = *absolute_magnification;
-                // sref.abs_angel = *absolute_angle;
+                sref.abs_magnification = *absolute_magnification;
+                sref.abs_angle = *absolute_angle;
             }
             Record::Mag(mag) => sref.magnific = *mag,
-            Record::Angle(angle) => sref.angle = std::f64::consts::PI / 180.0 * angle,
-            Record::Points(points) => {
-                sref.origin = Points::new(points[0].0 as f64 * factor, points[0].1 as f64 * factor)
-            }
-            Record::PropAttr(key)=>cur_prokey = Some(*key),
+            Record::Angle(angle) => sref.angle = Angle::from_degrees(*angle),
+            Record::Points(points) => sref.origin = first_point(points, factor)?,
+            Record::PropAttr(key) => cur_prokey = Some(*key),
             Record::PropValue(value) => {
-                if let Some(key) = cur_prokey{
-                    sref.property.0.insert(key, value.to_string());   
-                }else{
-                    return Err(Box::new(gds_err!(&std::format!(
+                if let Some(key) = cur_prokey {
+                    sref.property.0.insert(key, value.to_string());
+                } else {
+                    return Err(GdsError::InvalidRecordData(format!(
                         "Ref Property value \"{}\" have no key",
-                        &value))));
+                        value
+                    )));
                 }
             }
-            Record::EndElem => break,
-            other => {
-                println!("get record from ref {:#?}", other);
-            }
+            Record::EndElem => return Ok(sref),
+            other => reporter.report_at(
+                unexpected_record(other, "SREF element"),
+                iter.error_context(),
+            )?,
         }
     }
+    reporter.report_at(missing_endel(), iter.error_context())?;
     Ok(sref)
 }
 
-fn parse_aref(iter: &mut Iter<'_, Record>, factor: f64) -> Result<FakeRef, Box<dyn Error+Send+Sync>> {
+fn parse_aref(
+    iter: &mut RecordCursor<'_>,
+    factor: f64,
+    reporter: &mut Reporter,
+) -> Result<FakeRef, GdsError> {
     let mut aref = FakeRef::new();
-    let mut cur_prokey : Option<i16>= None;
-    while let Some(record) = iter.next() {
+    let mut anchors = RawAnchors::default();
+    let mut cur_prokey: Option<i16> = None;
+    while let Some(record) = next_element_record(iter) {
+        if let Some(raw) = iter.raw_record(&mut anchors, reporter)? {
+            aref.raw_records.push(raw);
+            continue;
+        }
         match record {
             Record::AryRef => (), // marks the beginning of an SREF(structure reference) element
-            Record::StrRefName(s) =>aref.refed_struc_name=s.to_string(),
+            Record::ElFlags(f) => aref.elflags = Some(ElFlags(*f)),
+            Record::Plex(p) => aref.plex = Some(Plex(*p)),
+            Record::StrRefName(s) => aref.refed_struc_name = s.to_string(),
             Record::RefTrans {
                 reflection_x,
-                ..
-                // absolute_magnification,
-                // absolute_angle,
+                absolute_magnification,
+                absolute_angle,
             } => {
                 aref.reflection_x = *reflection_x;
-                // aref.abs_magnific = *absolute_magnification;
-                // aref.abs_angel = *absolute_angle;
+                aref.abs_magnification = *absolute_magnification;
+                aref.abs_angle = *absolute_angle;
             }
             Record::Mag(mag) => aref.magnific = *mag,
-            Record::Angle(angle) => aref.angle = std::f64::consts::PI / 180.0 * angle,
+            Record::Angle(angle) => aref.angle = Angle::from_degrees(*angle),
             Record::ColRow { column, row } => {
                 aref.column = *column;
                 aref.row = *row;
             }
             Record::Points(points) => {
-                aref.origin = Points::new(points[0].0 as f64 * factor, points[0].1 as f64 * factor);
-                aref.spaceing_row =
-                    Vector::new((points[2].0 as f64 * factor - aref.origin.x)/aref.row as f64, 
-                    (points[2].1 as f64 * factor-aref.origin.y)/aref.row as f64);
-                aref.spaceing_col =
-                    Vector::new((points[1].0 as f64 * factor - aref.origin.x)/aref.column as f64, 
-                    (points[1].1 as f64 * factor- aref.origin.y)/aref.column as f64);
-            }
-            Record::PropAttr(key)=>cur_prokey = Some(*key),
+                // aref xy is origin, column displacement and row displacement
+                let [origin, col, row] = points[..] else {
+                    return Err(GdsError::InvalidRecordData(format!(
+                        "AREF should have 3 points, found {}",
+                        points.len()
+                    )));
+                };
+                // displacements are divided by COLROW, zero or missing COLROW is invalid
+                if aref.row == 0 || aref.column == 0 {
+                    return Err(GdsError::InvalidRecordData(format!(
+                        "AREF should have non-zero COLROW, found {} columns and {} rows",
+                        aref.column, aref.row
+                    )));
+                }
+                aref.origin = Points::new(origin.0 as f64 * factor, origin.1 as f64 * factor);
+                aref.spaceing_row = Vector::new(
+                    (row.0 as f64 * factor - aref.origin.x) / aref.row as f64,
+                    (row.1 as f64 * factor - aref.origin.y) / aref.row as f64,
+                );
+                aref.spaceing_col = Vector::new(
+                    (col.0 as f64 * factor - aref.origin.x) / aref.column as f64,
+                    (col.1 as f64 * factor - aref.origin.y) / aref.column as f64,
+                );
+            }
+            Record::PropAttr(key) => cur_prokey = Some(*key),
             Record::PropValue(value) => {
-                if let Some(key) = cur_prokey{
-                    aref.property.0.insert(key, value.to_string());   
-                }else{
-                    return Err(Box::new(gds_err!(&std::format!(
+                if let Some(key) = cur_prokey {
+                    aref.property.0.insert(key, value.to_string());
+                } else {
+                    return Err(GdsError::InvalidRecordData(format!(
                         "Ref Property value \"{}\" have no key",
-                        &value))));
+                        value
+                    )));
                 }
             }
-            Record::EndElem => break,
-            other => {
-                println!("get record from ref {:#?}", other);
-            }
+            Record::EndElem => return Ok(aref),
+            other => reporter.report_at(
+                unexpected_record(other, "AREF element"),
+                iter.error_context(),
+            )?,
         }
     }
+    reporter.report_at(missing_endel(), iter.error_context())?;
     Ok(aref)
 }
 
+// next record of element, ENDSTR is left to structure when ENDEL is missing
+fn next_element_record<'a>(iter: &mut RecordCursor<'a>) -> Option<&'a Record> {
+    match iter.peek() {
+        Some(Record::EndStr) => None,
+        _ => iter.next(),
+    }
+}
+
+fn missing_endel() -> GdsError {
+    GdsError::InvalidRecordData("element has no ENDEL record".to_string())
+}
+
+// point of single point XY record, like Text position or SRef origin
+fn first_point(points: &[(i32, i32)], factor: f64) -> Result<Points, GdsError> {
+    match points.first() {
+        Some(&(x, y)) => Ok(Points::new(x as f64 * factor, y as f64 * factor)),
+        None => Err(GdsError::InvalidRecordData(
+            "XY record has no point".to_string(),
+        )),
+    }
+}
+
 fn i32_vec_2_pointvec(vec: &[(i32, i32)], factor: f64) -> Vec<Points> {
     vec.iter()
         .map(|&(x, y)| Points::new(x as f64 * factor, y as f64 * factor))
         .collect()
 }
+
+#[cfg(test)]
+mod test_gds_parser {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_context() {
+        let mut lib = Lib::new("test");
+        let struc = Arc::new(RwLock::new(Struc::new("pad_ring")));
+        let mut polygon = Polygon::default();
+        polygon.points.push(Points::new(0.0, 0.0));
+        polygon.points.push(Points::new(1.0, 0.0));
+        polygon.points.push(Points::new(0.0, 1.0));
+        struc.write().unwrap().polygons.push(polygon);
+        struc.write().unwrap().paths.push(Path::default());
+        lib.add_struc(&struc).unwrap();
+        let mut bytes = lib.gds_bytes().unwrap();
+
+        // make path type invalid
+        let pathtype: &[u8] = &[0x00, 0x06, 0x21, 0x02, 0x00, 0x00];
+        let offset = bytes.windows(6).position(|w| w == pathtype).unwrap();
+        bytes[offset + 5] = 3;
+
+        let err = crate::read_gdsii_from(bytes.as_slice()).unwrap_err();
+        let context = err.context().unwrap();
+        assert_eq!(context.offset, offset as u64);
+        assert_eq!(context.record, Some("PATHTYPE"));
+        assert_eq!(context.structure.as_deref(), Some("pad_ring"));
+        assert_eq!(context.element, Some(("PATH", 1)));
+        assert!(err.to_string().starts_with(&format!(
+            "STRUCT 'pad_ring', PATH #1, PATHTYPE at offset {:#x}",
+            offset
+        )));
+    }
+
+    #[test]
+    fn test_malformed_input_no_panic() {
+        let mut lib = Lib::new("test");
+        let struc_1 = Arc::new(RwLock::new(Struc::new("cell_1")));
+        let struc_2 = Arc::new(RwLock::new(Struc::new("cell_2")));
+        let mut polygon = Polygon::default();
+        polygon.points.push(Points::new(0.0, 0.0));
+        polygon.points.push(Points::new(1.0, 0.0));
+        polygon.points.push(Points::new(0.0, 1.0));
+        polygon.property.0.insert(1, "prop".to_string());
+        let mut path = Path::default();
+        path.points.push(Points::new(0.0, 0.0));
+        path.points.push(Points::new(1.0, 0.0));
+        let text = Text {
+            text: "label".to_string(),
+            ..Default::default()
+        };
+        let mut aref = Ref::new(&struc_2);
+        aref.row = 2;
+        aref.column = 3;
+        {
+            let mut struc = struc_1.write().unwrap();
+            struc.polygons.push(polygon);
+            struc.paths.push(path);
+            struc.label.push(text);
+            struc.refs.push(Ref::new(&struc_2));
+            struc.refs.push(aref);
+        }
+        lib.add_struc(&struc_1).unwrap();
+        let bytes = lib.gds_bytes().unwrap();
+        assert!(crate::read_gdsii_from(bytes.as_slice()).is_ok());
+
+        // zero COLROW of aref
+        let colrow: &[u8] = &[0x00, 0x08, 0x13, 0x02, 0x00, 0x03, 0x00, 0x02];
+        let offset = bytes.windows(8).position(|w| w == colrow).unwrap();
+        let mut zero_colrow = bytes.clone();
+        zero_colrow[offset + 6..offset + 8].fill(0);
+        let err = crate::read_gdsii_from(zero_colrow.as_slice()).unwrap_err();
+        assert!(matches!(err.root(), GdsError::InvalidRecordData(_)));
+
+        let lenient = ReadOptions {
+            strict: false,
+            ..Default::default()
+        };
+        // truncated data
+        for len in 0..bytes.len() {
+            let _ = crate::read_gdsii_from(&bytes[..len]);
+            let _ = crate::read_gdsii_from_with_options(&bytes[..len], &lenient);
+        }
+        // corrupted data
+        for idx in 0..bytes.len() {
+            for v in [0x00, 0x01, 0x7f, 0xff, bytes[idx] ^ 0x01] {
+                let mut corrupted = bytes.clone();
+                corrupted[idx] = v;
+                let _ = crate::read_gdsii_from(corrupted.as_slice());
+                let _ = crate::read_gdsii_from_with_options(corrupted.as_slice(), &lenient);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lenient_read() {
+        let mut lib = Lib::new("test");
+        let top = Arc::new(RwLock::new(Struc::new("top")));
+        let sub = Arc::new(RwLock::new(Struc::new("sub")));
+        let mut polygon = Polygon::default();
+        polygon.points.push(Points::new(0.0, 0.0));
+        polygon.points.push(Points::new(1.0, 0.0));
+        polygon.points.push(Points::new(0.0, 1.0));
+        {
+            let mut struc = top.write().unwrap();
+            struc.polygons.push(polygon);
+            struc.refs.push(Ref::new(&sub));
+        }
+        lib.add_struc(&top).unwrap();
+        let mut bytes = lib.gds_bytes().unwrap();
+
+        // refer to missing structure, "sub" is renamed to "sux"
+        let strname: &[u8] = &[0x00, 0x08, 0x06, 0x06, b's', b'u', b'b', 0x00];
+        let offset = bytes.windows(8).position(|w| w == strname).unwrap();
+        bytes[offset + 6] = b'x';
+        // misplaced SNAME record before ENDEL of polygon
+        let endel: &[u8] = &[0x00, 0x04, 0x11, 0x00];
+        let endel_offset = bytes.windows(4).position(|w| w == endel).unwrap();
+        bytes.splice(endel_offset..endel_offset, [0x00, 0x04, 0x12, 0x06]);
+        // garbage after ENDLIB
+        bytes.extend_from_slice(&[0x00, 0x00, 0xab]);
+
+        assert!(crate::read_gdsii_from(bytes.as_slice()).is_err());
+
+        let options = ReadOptions {
+            strict: false,
+            ..Default::default()
+        };
+        let (lib, diagnostics) =
+            crate::read_gdsii_from_with_options(bytes.as_slice(), &options).unwrap();
+        let strucs = lib.all_strucs();
+        let top = strucs.iter().find(|s| s.read().unwrap().name == "top");
+        let top = top.unwrap().read().unwrap();
+        assert_eq!(top.polygons.len(), 1);
+        assert!(top.refs.is_empty());
+        assert!(strucs.iter().any(|s| s.read().unwrap().name == "sux"));
+        assert_eq!(diagnostics[0].severity, Severity::Info);
+        assert_eq!(diagnostics[0].message, "read GDSII version: 600");
+        let diagnostics: Vec<_> = diagnostics
+            .into_iter()
+            .filter(|d| d.severity == Severity::Warning)
+            .collect();
+        assert_eq!(diagnostics.len(), 3);
+        assert!(diagnostics.windows(2).all(|d| d[0].offset <= d[1].offset));
+        assert_eq!(diagnostics[0].offset, endel_offset as u64);
+        assert!(diagnostics[1]
+            .message
+            .contains("refered struc named sub not found"));
+        assert_eq!(diagnostics[2].offset, bytes.len() as u64 - 1);
+    }
+
+    #[test]
+    fn test_raw_record_after_undefined_first_ref() {
+        let mut lib = Lib::new("test");
+        let top = Arc::new(RwLock::new(Struc::new("top")));
+        let sub = Arc::new(RwLock::new(Struc::new("sub")));
+        let other = Arc::new(RwLock::new(Struc::new("other")));
+        {
+            let mut struc = top.write().unwrap();
+            struc.refs.push(Ref::new(&sub));
+            struc.refs.push(Ref::new(&other));
+        }
+        lib.add_struc(&top).unwrap();
+        let mut bytes = lib.gds_bytes().unwrap();
+
+        // first ref refers to missing structure, "sub" is renamed to "sux"
+        let strname: &[u8] = &[0x00, 0x08, 0x06, 0x06, b's', b'u', b'b', 0x00];
+        let offset = bytes.windows(8).position(|w| w == strname).unwrap();
+        bytes[offset + 6] = b'x';
+        // raw record after first ref
+        let sref: &[u8] = &[0x00, 0x04, 0x0a, 0x00];
+        let endel: &[u8] = &[0x00, 0x04, 0x11, 0x00];
+        let sref_offset = bytes.windows(4).position(|w| w == sref).unwrap();
+        let endel_offset = sref_offset
+            + bytes[sref_offset..]
+                .windows(4)
+                .position(|w| w == endel)
+                .unwrap();
+        bytes.splice(
+            endel_offset + 4..endel_offset + 4,
+            [0x00, 0x06, 0x43, 0x06, b'u', b'c'],
+        );
+
+        let options = ReadOptions {
+            strict: false,
+            ..Default::default()
+        };
+        let (lib, _) = crate::read_gdsii_from_with_options(bytes.as_slice(), &options).unwrap();
+        let top = lib
+            .all_strucs()
+            .into_iter()
+            .find(|s| s.read().unwrap().name == "top");
+        let top = top.unwrap();
+        let top = top.read().unwrap();
+        assert_eq!(top.refs.len(), 1);
+        assert_eq!(top.refs[0].refed_struc.read().unwrap().name, "other");
+        // follows header of structure instead of ref to "other"
+        assert_eq!(
+            top.raw_records[0].anchor,
+            RawAnchor::Record {
+                record_type: STRNAME[0],
+                occurrence: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_sink() {
+        struct Collector(std::sync::Mutex<Vec<Diagnostic>>);
+        impl crate::DiagnosticSink for Collector {
+            fn report(&self, diagnostic: &Diagnostic) {
+                self.0.lock().unwrap().push(diagnostic.clone());
+            }
+        }
+
+        let lib = Lib::new("test");
+        let bytes = lib.gds_bytes().unwrap();
+        let collector = Arc::new(Collector(Default::default()));
+        let options = ReadOptions {
+            sink: collector.clone(),
+            ..Default::default()
+        };
+        let (_, diagnostics) =
+            crate::read_gdsii_from_with_options(bytes.as_slice(), &options).unwrap();
+        let reported = collector.0.lock().unwrap();
+        assert_eq!(reported.len(), 1);
+        assert_eq!(reported[0].severity, Severity::Info);
+        assert_eq!(reported[0].message, diagnostics[0].message);
+        drop(reported);
+
+        // diagnostics before an error are sent too
+        let mut bytes = bytes;
+        bytes[4..6].copy_from_slice(&601_i16.to_be_bytes());
+        assert!(crate::read_gdsii_from_with_options(bytes.as_slice(), &options).is_err());
+        let reported = collector.0.lock().unwrap();
+        assert_eq!(reported.len(), 2);
+        assert_eq!(reported[1].message, "read GDSII version: 601");
+    }
+
+    #[test]
+    fn test_raw_record_round_trip() {
+        let mut lib = Lib::new("test");
+        let struc = Arc::new(RwLock::new(Struc::new("cell")));
+        let polygon = Polygon {
+            points: vec![
+                Points::new(0.0, 0.0),
+                Points::new(1.0, 0.0),
+                Points::new(0.0, 1.0),
+            ],
+            ..Default::default()
+        };
+        struc.write().unwrap().polygons.push(polygon);
+        lib.add_struc(&struc).unwrap();
+        let mut bytes = lib.gds_bytes().unwrap();
+
+        // HARDWIRE before ENDEL of polygon
+        let endel: &[u8] = &[0x00, 0x04, 0x11, 0x00];
+        let offset = bytes.windows(4).position(|w| w == endel).unwrap();
+        bytes.splice(offset..offset, [0x00, 0x04, 0x40, 0x00]);
+        // USERCONSTRAINT after STRNAME
+        let strname: &[u8] = &[0x00, 0x08, 0x06, 0x06, b'c', b'e', b'l', b'l'];
+        let offset = bytes.windows(8).position(|w| w == strname).unwrap() + 8;
+        bytes.splice(offset..offset, [0x00, 0x06, 0x43, 0x06, b'u', b'c']);
+        // LIBSECUR after BGNLIB
+        bytes.splice(
+            34..34,
+            [0x00, 0x0a, 0x3b, 0x02, 0x00, 0x01, 0x00, 0x02, 0x00, 0x03],
+        );
+
+        let read_lib = crate::read_gdsii_from(bytes.as_slice()).unwrap();
+        assert_eq!(
+            read_lib.raw_records,
+            [RawRecord {
+                anchor: RawAnchor::Record {
+                    record_type: BGNLIB[0],
+                    occurrence: 0,
+                },
+                record_type: 0x3b,
+                data_type: 0x02,
+                bytes: vec![0x00, 0x01, 0x00, 0x02, 0x00, 0x03],
+            }]
+        );
+        let read_struc = read_lib.all_strucs()[0].clone();
+        let read_struc = read_struc.read().unwrap();
+        assert_eq!(read_struc.raw_records.len(), 1);
+        assert_eq!(
+            read_struc.raw_records[0].anchor,
+            RawAnchor::Record {
+                record_type: STRNAME[0],
+                occurrence: 0,
+            }
+        );
+        assert_eq!(read_struc.raw_records[0].bytes, b"uc");
+        let raw = &read_struc.polygons[0].raw_records;
+        assert_eq!(raw.len(), 1);
+        assert_eq!(raw[0].record_type, 0x40);
+        assert_eq!(
+            raw[0].anchor,
+            RawAnchor::Record {
+                record_type: XY[0],
+                occurrence: 0,
+            }
+        );
+        assert_eq!(read_lib.gds_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_undefined_record_type() {
+        let mut lib = Lib::new("test");
+        let struc = Arc::new(RwLock::new(Struc::new("cell")));
+        struc.write().unwrap().polygons.push(Polygon {
+            points: vec![
+                Points::new(0.0, 0.0),
+                Points::new(1.0, 0.0),
+                Points::new(0.0, 1.0),
+            ],
+            ..Default::default()
+        });
+        lib.add_struc(&struc).unwrap();
+        let mut bytes = lib.gds_bytes().unwrap();
+        // record type 0x50 is not defined by gds manual, before ENDEL of polygon
+        let endel: &[u8] = &[0x00, 0x04, 0x11, 0x00];
+        let endel_offset = bytes.windows(4).position(|w| w == endel).unwrap();
+        bytes.splice(
+            endel_offset..endel_offset,
+            [0x00, 0x06, 0x50, 0x02, 0x00, 0x07],
+        );
+
+        let err = crate::read_gdsii_from(bytes.as_slice()).unwrap_err();
+        assert!(matches!(
+            err.root(),
+            GdsError::UnknownRecord {
+                record_type: 0x50,
+                data_type: 0x02,
+                ..
+            }
+        ));
+        assert_eq!(err.context().unwrap().offset, endel_offset as u64);
+
+        // and 0x5a before ENDLIB
+        let endlib = bytes.len() - 4;
+        bytes.splice(endlib..endlib, [0x00, 0x04, 0x5a, 0x00]);
+
+        let options = ReadOptions {
+            strict: false,
+            ..Default::default()
+        };
+        let (read_lib, diagnostics) =
+            crate::read_gdsii_from_with_options(bytes.as_slice(), &options).unwrap();
+        let warnings: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+            .collect();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].offset, endel_offset as u64);
+        assert_eq!(read_lib.raw_records[0].record_type, 0x5a);
+        let read_struc = read_lib.all_strucs()[0].clone();
+        let raw = &read_struc.read().unwrap().polygons[0].raw_records;
+        assert_eq!((raw[0].record_type, raw[0].data_type), (0x50, 0x02));
+        assert_eq!(raw[0].bytes, [0x00, 0x07]);
+        assert_eq!(read_lib.gds_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_raw_record_between_elements() {
+        let mut lib = Lib::new("test");
+        let struc = Arc::new(RwLock::new(Struc::new("cell")));
+        {
+            let mut struc = struc.write().unwrap();
+            for _ in 0..2 {
+                struc.polygons.push(Polygon {
+                    points: vec![
+                        Points::new(0.0, 0.0),
+                        Points::new(1.0, 0.0),
+                        Points::new(0.0, 1.0),
+                    ],
+                    ..Default::default()
+                });
+            }
+            struc.label.push(Text {
+                text: "t".to_string(),
+                ..Default::default()
+            });
+        }
+        lib.add_struc(&struc).unwrap();
+        // written grouped: BOUNDARY, BOUNDARY, TEXT
+        let grouped = lib.gds_bytes().unwrap();
+        let boundary: &[u8] = &[0x00, 0x04, 0x08, 0x00];
+        let text: &[u8] = &[0x00, 0x04, 0x0c, 0x00];
+        let endel: &[u8] = &[0x00, 0x04, 0x11, 0x00];
+        let second = grouped.windows(4).rposition(|w| w == boundary).unwrap();
+        let text_start = grouped.windows(4).position(|w| w == text).unwrap();
+        let text_end = grouped.windows(4).rposition(|w| w == endel).unwrap() + 4;
+        let user_constraint: &[u8] = &[0x00, 0x06, 0x43, 0x06, b'u', b'c'];
+        // BOUNDARY, TEXT, USERCONSTRAINT, BOUNDARY
+        let bytes = [
+            &grouped[..second],
+            &grouped[text_start..text_end],
+            user_constraint,
+            &grouped[second..text_start],
+            &grouped[text_end..],
+        ]
+        .concat();
+
+        let read_lib = crate::read_gdsii_from(bytes.as_slice()).unwrap();
+        let read_struc = read_lib.all_strucs()[0].clone();
+        assert_eq!(
+            read_struc.read().unwrap().raw_records[0].anchor,
+            RawAnchor::Element {
+                kind: ElementKind::Text,
+                index: 0,
+            }
+        );
+        // still follows TEXT when elements are written grouped
+        let expected = [&grouped[..text_end], user_constraint, &grouped[text_end..]].concat();
+        assert_eq!(read_lib.gds_bytes().unwrap(), expected);
+    }
+}
@@ -1,22 +1,27 @@
 use super::gds_model;
 use super::gds_record;
-use std::error::Error;
+use crate::gds_diagnostic::{DiagnosticSink, LogSink};
+use crate::gds_error::{ErrorContext, GdsError};
+use std::io::{BufReader, ErrorKind, Read, Seek};
+use std::sync::Arc;
 
-fn two_byte_int(byte: &[u8]) -> Result<i16, Box<dyn Error>> {
+fn two_byte_int(byte: &[u8]) -> Result<i16, GdsError> {
     let byte_len = byte.len();
-    if byte_len % 2 != 0 {
-        return Err(Box::new(gds_err!(
-            "transfer two byte int failed: byte length % 2 != 0"
+    if byte_len < 2 || !byte_len.is_multiple_of(2) {
+        return Err(GdsError::InvalidRecordData(format!(
+            "transfer two byte int failed: byte length {} is not multiple of 2",
+            byte_len
         )));
     }
     Ok(i16::from_be_bytes(byte[0..2].try_into()?))
 }
 
-fn two_byte_int_2(byte: &[u8]) -> Result<(i16, i16), Box<dyn Error>> {
+fn two_byte_int_2(byte: &[u8]) -> Result<(i16, i16), GdsError> {
     let byte_len = byte.len();
-    if byte_len % 4 != 0 {
-        return Err(Box::new(gds_err!(
-            "transfer two byte int failed: byte length % 4 != 0"
+    if byte_len < 4 || !byte_len.is_multiple_of(4) {
+        return Err(GdsError::InvalidRecordData(format!(
+            "transfer two byte int failed: byte length {} is not multiple of 4",
+            byte_len
         )));
     }
     Ok((
@@ -25,12 +30,12 @@ fn two_byte_int_2(byte: &[u8]) -> Result<(i16, i16), Box<dyn Error>> {
     ))
 }
 
-fn two_byte_int_array(byte: &[u8]) -> Result<Vec<i16>, Box<dyn Error>> {
+fn two_byte_int_array(byte: &[u8]) -> Result<Vec<i16>, GdsError> {
     let byte_len = byte.len();
-    if byte_len % 2 != 0 {
-        return Err(Box::new(gds_err!(
-            "transfer two byte int failed: byte length % 2 != 0"
-        )));
+    if !byte_len.is_multiple_of(2) {
+        return Err(GdsError::InvalidRecordData(
+            "transfer two byte int failed: byte length % 2 != 0".to_string(),
+        ));
     }
     let mut value: Vec<i16> = Vec::with_capacity(byte_len / 2);
     for i in (0..byte_len).step_by(2) {
@@ -39,12 +44,12 @@ fn two_byte_int_array(byte: &[u8]) -> Result<Vec<i16>, Box<dyn Error>> {
     Ok(value)
 }
 
-fn four_byte_int_xy(byte: &[u8]) -> Result<Vec<(i32, i32)>, Box<dyn Error>> {
+fn four_byte_int_xy(byte: &[u8]) -> Result<Vec<(i32, i32)>, GdsError> {
     let byte_len = byte.len();
-    if byte_len % 8 != 0 {
-        return Err(Box::new(gds_err!(
-            "transfer four byte int failed: byte length % 8 != 0"
-        )));
+    if !byte_len.is_multiple_of(8) {
+        return Err(GdsError::InvalidRecordData(
+            "transfer four byte int failed: byte length % 8 != 0".to_string(),
+        ));
     }
     let mut value: Vec<(i32, i32)> = Vec::with_capacity(byte_len / 8);
     for i in (0..byte_len).step_by(8) {
@@ -56,11 +61,12 @@ fn four_byte_int_xy(byte: &[u8]) -> Result<Vec<(i32, i32)>, Box<dyn Error>> {
     Ok(value)
 }
 
-fn four_byte_int(byte: &[u8]) -> Result<i32, Box<dyn Error>> {
+fn four_byte_int(byte: &[u8]) -> Result<i32, GdsError> {
     let byte_len = byte.len();
-    if byte_len % 4 != 0 {
-        return Err(Box::new(gds_err!(
-            "transfer four byte int failed: byte length % 4 != 0"
+    if byte_len < 4 || !byte_len.is_multiple_of(4) {
+        return Err(GdsError::InvalidRecordData(format!(
+            "transfer four byte int failed: byte length {} is not multiple of 4",
+            byte_len
         )));
     }
     Ok(i32::from_be_bytes(byte[0..4].try_into()?))
@@ -78,11 +84,13 @@ fn four_byte_int(byte: &[u8]) -> Result<i32, Box<dyn Error>> {
 /// SEEEEEEE EEEEMMMM MMMMMMMM MMMMMMMM
 /// MMMMMMMM MMMMMMMM MMMMMMMM MMMMMMMM
 /// and value = (-1)^S*2^(E as u32 -1023)*(1+M as u64/2^52)
-pub(crate) fn gdsii_eight_byte_real(byte: &[u8]) -> Result<f64, Box<dyn Error>> {
+///
+/// 56 bit M is rounded to 53 bit of f64 half to even, scaling by power of 2 is exact
+pub(crate) fn gdsii_eight_byte_real(byte: &[u8]) -> Result<f64, GdsError> {
     if byte.len() != 8 {
-        return Err(Box::new(gds_err!(
-            "transfer eight byte real failed: byte length != 8"
-        )));
+        return Err(GdsError::InvalidRecordData(
+            "transfer eight byte real failed: byte length != 8".to_string(),
+        ));
     }
     // 0x7F is 0b0111_1111, get all E bit, convert to i32
     let exponent = (byte[0] & 0x7F) as i32;
@@ -97,40 +105,56 @@ pub(crate) fn gdsii_eight_byte_real(byte: &[u8]) -> Result<f64, Box<dyn Error>>
     }
 }
 
-fn eight_byte_real(byte: &[u8]) -> Result<Vec<f64>, Box<dyn Error>> {
+fn eight_byte_real(byte: &[u8]) -> Result<Vec<f64>, GdsError> {
     let byte_len = byte.len();
-    if byte_len % 8 != 0 {
-        return Err(Box::new(gds_err!(
-            "transfer eight byte real failed: byte length % 8 != 0"
-        )));
+    if !byte_len.is_multiple_of(8) {
+        return Err(GdsError::InvalidRecordData(
+            "transfer eight byte real failed: byte length % 8 != 0".to_string(),
+        ));
     }
     let mut value: Vec<f64> = Vec::with_capacity(byte_len / 8);
     for i in (0..byte_len).step_by(8) {
-        value.push(gdsii_eight_byte_real(byte[i..i + 8].try_into()?)?);
+        value.push(gdsii_eight_byte_real(&byte[i..i + 8])?);
     }
     Ok(value)
 }
 
-fn ascii_string(byte: &[u8]) -> Result<String, Box<dyn Error>> {
-    let strip_none = if *(byte.last().unwrap()) == 0 {
-        &byte[..byte.len() - 1]
-    } else {
-        byte
-    };
+fn ascii_string(byte: &[u8]) -> Result<String, GdsError> {
+    // odd length string is padded with a null char
+    let strip_none = byte.strip_suffix(&[0]).unwrap_or(byte);
     let s = String::from_utf8(strip_none.to_vec())?;
     if s.is_ascii() {
         Ok(s)
     } else {
-        Err(Box::new(gds_err!(&format!(
+        Err(GdsError::InvalidRecordData(format!(
             "{} contains char not in ascii charset",
             s
-        ))))
+        )))
+    }
+}
+
+// library and font file names are stored in fields of 44 bytes, padded with null chars
+fn fixed_ascii_strings(byte: &[u8]) -> Result<Vec<String>, GdsError> {
+    if byte.is_empty() || !byte.len().is_multiple_of(44) {
+        return Err(GdsError::InvalidRecordData(format!(
+            "file name record length {} is not multiple of 44 bytes",
+            byte.len()
+        )));
     }
+    byte.chunks(44)
+        .map(|name| {
+            let end = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+            ascii_string(&name[..end])
+        })
+        .collect()
 }
 
-pub fn record_type(bytes: &[u8]) -> Result<gds_record::Record, Box<dyn Error>> {
+/// decode one gds record
+pub fn record_type(bytes: &[u8]) -> Result<gds_record::Record, GdsError> {
     if bytes.len() < 4 {
-        return Err(Box::new(gds_err!("gds record length less than 4 bytes")));
+        return Err(GdsError::InvalidRecordData(
+            "gds record length less than 4 bytes".to_string(),
+        ));
     }
     let record = &bytes[2..4];
     let data = &bytes[4..];
@@ -151,11 +175,16 @@ pub fn record_type(bytes: &[u8]) -> Result<gds_record::Record, Box<dyn Error>> {
         // and the following special characters: &, %, $, #, @, ^, *, and _
         gds_record::LIBNAME => Ok(gds_record::Record::LibName(ascii_string(data)?)),
         gds_record::UNITS => {
-            let unit = eight_byte_real(data)?;
-            Ok(gds_record::Record::Units {
-                unit_in_meter: unit[0],
-                precision: unit[1],
-            })
+            if let [unit_in_meter, precision] = eight_byte_real(data)?[..] {
+                Ok(gds_record::Record::Units {
+                    unit_in_meter,
+                    precision,
+                })
+            } else {
+                Err(GdsError::InvalidRecordData(
+                    "units record should have 2 eight byte real".to_string(),
+                ))
+            }
         }
         gds_record::ENDLIB => Ok(gds_record::Record::EndLib),
         gds_record::BGNSTR => {
@@ -208,9 +237,10 @@ pub fn record_type(bytes: &[u8]) -> Result<gds_record::Record, Box<dyn Error>> {
             })
         }
         // TEXTNODE => Record::TEXTNODE,
-        // NODE => Record::NODE,
+        gds_record::NODE => Ok(gds_record::Record::Node),
         gds_record::TEXTTYPE => Ok(gds_record::Record::TextType(two_byte_int(data)?)),
         gds_record::PRESENTATION => {
+            let data = two_byte_int(data)?.to_be_bytes();
             let font_tag = data[1] & 0b0011_0000;
             let ver_tag = data[1] & 0b0000_1100;
             let hor_tag = data[1] & 0b0000_0011;
@@ -224,7 +254,7 @@ pub fn record_type(bytes: &[u8]) -> Result<gds_record::Record, Box<dyn Error>> {
                 } else if font_tag == 0b0011_0000 {
                     gds_record::PresentationFont::Fonts3
                 } else {
-                    return Err(Box::new(gds_err!("Unknown font type")));
+                    return Err(GdsError::InvalidRecordData("Unknown font type".to_string()));
                 },
                 vertival_justfication: if ver_tag == 0b0000_0000 {
                     gds_record::PresentationVerticalPos::Top
@@ -233,7 +263,9 @@ pub fn record_type(bytes: &[u8]) -> Result<gds_record::Record, Box<dyn Error>> {
                 } else if ver_tag == 0b0000_1000 {
                     gds_record::PresentationVerticalPos::Bottom
                 } else {
-                    return Err(Box::new(gds_err!("Unknown vertical type")));
+                    return Err(GdsError::InvalidRecordData(
+                        "Unknown vertical type".to_string(),
+                    ));
                 },
                 horizontal_justfication: if hor_tag == 0b0000_0000 {
                     gds_record::PresentationHorizontalPos::Left
@@ -242,7 +274,9 @@ pub fn record_type(bytes: &[u8]) -> Result<gds_record::Record, Box<dyn Error>> {
                 } else if hor_tag == 0b0000_0010 {
                     gds_record::PresentationHorizontalPos::Right
                 } else {
-                    return Err(Box::new(gds_err!("Unknown horizontal type")));
+                    return Err(GdsError::InvalidRecordData(
+                        "Unknown horizontal type".to_string(),
+                    ));
                 },
             })
         }
@@ -250,34 +284,56 @@ pub fn record_type(bytes: &[u8]) -> Result<gds_record::Record, Box<dyn Error>> {
         gds_record::STRING => {
             let s = ascii_string(data)?;
             if s.len() > 512 {
-                return Err(Box::new(gds_err!("Lib string exceed 512 chars")));
+                return Err(GdsError::InvalidRecordData(
+                    "Lib string exceed 512 chars".to_string(),
+                ));
             }
             Ok(gds_record::Record::String(s))
         }
-        gds_record::STRANS => Ok(gds_record::Record::RefTrans {
-            // test bit 0
-            reflection_x: if data[0] & 0x80 != 0 { true } else { false },
-            // test bit 13
-            absolute_magnification: if data[1] & 0x04 != 0 { true } else { false },
-            // test bit 14
-            absolute_angle: if data[1] & 0x02 != 0 { true } else { false },
-        }),
-        gds_record::MAG => Ok(gds_record::Record::Mag(eight_byte_real(data)?[0])),
-        gds_record::ANGLE => Ok(gds_record::Record::Angle(eight_byte_real(data)?[0])),
+        gds_record::STRANS => {
+            let data = two_byte_int(data)?.to_be_bytes();
+            Ok(gds_record::Record::RefTrans {
+                // test bit 0
+                reflection_x: data[0] & 0x80 != 0,
+                // test bit 13
+                absolute_magnification: data[1] & 0x04 != 0,
+                // test bit 14
+                absolute_angle: data[1] & 0x02 != 0,
+            })
+        }
+        gds_record::MAG => Ok(gds_record::Record::Mag(gdsii_eight_byte_real(data)?)),
+        gds_record::ANGLE => Ok(gds_record::Record::Angle(gdsii_eight_byte_real(data)?)),
         // UINTEGER => Record::UINTEGER,
         // USTRING => Record::USTRING,
-        // REFLIBS => Record::REFLIBS,
-        // FONTS => Record::FONTS,
+        gds_record::REFLIBS => Ok(gds_record::Record::RefLibs(fixed_ascii_strings(data)?)),
+        gds_record::FONTS => {
+            let fonts = fixed_ascii_strings(data)?;
+            if fonts.len() != 4 {
+                return Err(GdsError::InvalidRecordData(format!(
+                    "fonts record should have 4 font names, found {}",
+                    fonts.len()
+                )));
+            }
+            Ok(gds_record::Record::Fonts(fonts))
+        }
         gds_record::PATHTYPE => Ok(gds_record::Record::PathType(two_byte_int(data)?)),
-        // GENERATIONS => Record::GENERATIONS,
-        // ATTRTABLE => Record::ATTRTABLE,
+        gds_record::GENERATIONS => Ok(gds_record::Record::Generations(two_byte_int(data)?)),
+        gds_record::ATTRTABLE => {
+            let s = ascii_string(data)?;
+            if s.len() > 44 {
+                return Err(GdsError::InvalidRecordData(
+                    "attribute table file name exceed 44 chars".to_string(),
+                ));
+            }
+            Ok(gds_record::Record::AttrTable(s))
+        }
         // STYPTABLE => Record::STYPTABLE,
         // STRTYPE => Record::STRTYPE,
-        // ELFLAGS => Record::ELFLAGS,
+        gds_record::ELFLAGS => Ok(gds_record::Record::ElFlags(two_byte_int(data)? as u16)),
         // ELKEY => Record::ELKEY,
         // LINKTYPE => Record::LINKTYPE,
         // LINKKEYS => Record::LINKKEYS,
-        // NODETYPE => Record::NODETYPE,
+        gds_record::NODETYPE => Ok(gds_record::Record::NodeType(two_byte_int(data)?)),
         gds_record::PROPATTR => {
             // TODO:
             // manual require number is an integer from 1 to 127. Attribute numbers 126 and 127 are reserved
@@ -287,24 +343,26 @@ pub fn record_type(bytes: &[u8]) -> Result<gds_record::Record, Box<dyn Error>> {
         gds_record::PROPVALUE => {
             let s = ascii_string(data)?;
             if s.len() > 126 {
-                return Err(Box::new(gds_err!("Property value record exceed 126 chars")));
+                return Err(GdsError::InvalidRecordData(
+                    "Property value record exceed 126 chars".to_string(),
+                ));
             }
             Ok(gds_record::Record::PropValue(s))
         }
         gds_record::BOX => Ok(gds_record::Record::Box),
         gds_record::BOXTYPE => Ok(gds_record::Record::BoxType(two_byte_int(data)?)),
-        // PLEX => Record::PLEX,
-        // BGNEXTN => Record::BGNEXTN,
-        // ENDEXTN => Record::ENDEXTN,
+        gds_record::PLEX => Ok(gds_record::Record::Plex(four_byte_int(data)?)),
+        gds_record::BGNEXTN => Ok(gds_record::Record::BgnExtn(four_byte_int(data)?)),
+        gds_record::ENDEXTN => Ok(gds_record::Record::EndExtn(four_byte_int(data)?)),
         // TAPENUM => Record::TAPENUM,
         // TAPECODE => Record::TAPECODE,
-        // STRCLASS => Record::STRCLASS,
+        gds_record::STRCLASS => Ok(gds_record::Record::StrClass(two_byte_int(data)? as u16)),
         // RESERVED => Record::RESERVED,
-        // FORMAT => Record::FORMAT,
-        // MASK => Record::MASK,
-        // ENDMASKS => Record::ENDMASKS,
+        gds_record::FORMAT => Ok(gds_record::Record::Format(two_byte_int(data)?)),
+        gds_record::MASK => Ok(gds_record::Record::Mask(ascii_string(data)?)),
+        gds_record::ENDMASKS => Ok(gds_record::Record::EndMasks),
         // LIBDIRSIZE => Record::LIBDIRSIZE,
-        // SRFNAME => Record::SRFNAME,
+        gds_record::SRFNAME => Ok(gds_record::Record::SrfName(ascii_string(data)?)),
         // LIBSECUR => Record::LIBSECUR,
         // BORDER => Record::BORDER,
         // SOFTFENCE => Record::SOFTFENCE,
@@ -316,13 +374,411 @@ pub fn record_type(bytes: &[u8]) -> Result<gds_record::Record, Box<dyn Error>> {
         // USERCONSTRAINT => Record::USERCONSTRAINT,
         // SPACERERROR => Record::SPACERERROR,
         // CONTACT => Record::CONTACT,
-        _ => Err(Box::new(gds_err!(&format!(
-            "Error: unkonw record {:#02x?}",
-            bytes
-        )))),
+        // records not decoded, like vendor records or record types not defined by gds manual,
+        // are kept as is
+        _ => Ok(gds_record::Record::Unknown {
+            record_type: record[0],
+            data_type: record[1],
+            bytes: data.to_vec(),
+        }),
+    }
+}
+
+/// Options of reading gds data
+#[derive(Clone)]
+pub struct ReadOptions {
+    /// fail on first anomaly like unknown or misplaced record, reference to missing structure
+    /// or garbage after ENDLIB
+    ///
+    /// if false, anomaly is skipped and reported as [`Diagnostic`](crate::Diagnostic),
+    /// so as much of the library as possible is loaded
+    ///
+    /// default is true
+    pub strict: bool,
+    /// receive all diagnostics after reading, nothing is printed to stdout
+    ///
+    /// default forwards diagnostics to `log` facade
+    pub sink: Arc<dyn DiagnosticSink>,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions {
+            strict: true,
+            sink: Arc::new(LogSink),
+        }
+    }
+}
+
+impl std::fmt::Debug for ReadOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadOptions")
+            .field("strict", &self.strict)
+            .finish_non_exhaustive()
     }
 }
 
+/// Read gds records one by one from any byte stream
+///
+/// only a single record is buffered at a time, so files of any size can be
+/// read from pipes, sockets or decompressors without staging them in memory
+pub struct RecordReader<R: Read> {
+    reader: BufReader<R>,
+    buff: Vec<u8>,
+    offset: u64,
+    record_offset: u64,
+    record_count: usize,
+    failed: bool,
+}
+
+impl<R: Read> RecordReader<R> {
+    pub fn new(reader: R) -> Self {
+        RecordReader {
+            reader: BufReader::new(reader),
+            buff: Vec::new(),
+            offset: 0,
+            record_offset: 0,
+            record_count: 0,
+            failed: false,
+        }
+    }
+
+    /// byte offset of the last record returned by [`RecordReader::read_record`]
+    pub fn record_offset(&self) -> u64 {
+        self.record_offset
+    }
+
+    /// index of the last record returned by [`RecordReader::read_record`], starting from 0
+    pub fn record_index(&self) -> usize {
+        self.record_count.saturating_sub(1)
+    }
+
+    /// read next gds record, return `Ok(None)` when stream ends at a record boundary
+    ///
+    /// a record that can not be decoded is returned as error, reading can go on with next record,
+    /// after a broken or truncated record is found, no more record will be read
+    pub fn read_record(&mut self) -> Result<Option<gds_record::Record>, GdsError> {
+        Ok(self.read_pos_record()?.map(|r| r.record))
+    }
+
+    // iterate records with their position
+    pub(crate) fn pos_records(
+        &mut self,
+    ) -> impl Iterator<Item = Result<gds_record::PosRecord, GdsError>> + '_ {
+        std::iter::from_fn(move || self.read_pos_record().transpose())
+    }
+
+    /// offset of first non-zero byte after current position, consume all remaining data
+    ///
+    /// used to find garbage after ENDLIB, which may be padded with null bytes
+    pub(crate) fn trailing_data_offset(&mut self) -> Result<Option<u64>, GdsError> {
+        let mut trailing = None;
+        let mut buff = [0_u8; 4096];
+        loop {
+            let read_len = read_full(&mut self.reader, &mut buff)?;
+            if trailing.is_none() {
+                if let Some(pos) = buff[..read_len].iter().position(|b| *b != 0) {
+                    trailing = Some(self.offset + pos as u64);
+                }
+            }
+            self.offset += read_len as u64;
+            if read_len < buff.len() {
+                return Ok(trailing);
+            }
+        }
+    }
+
+    fn read_pos_record(&mut self) -> Result<Option<gds_record::PosRecord>, GdsError> {
+        if self.failed {
+            return Ok(None);
+        }
+        let context = match self.read_record_bytes() {
+            Ok(Some(context)) => context,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                // stream position is unknown after a broken record
+                self.failed = true;
+                return Err(e);
+            }
+        };
+        let record = record_type(&self.buff).map_err(|e| {
+            e.in_context(ErrorContext {
+                record: gds_record::record_name(self.buff[2]),
+                ..context.clone()
+            })
+        })?;
+        Ok(Some(gds_record::PosRecord {
+            offset: context.offset,
+            index: context.record_index,
+            record_type: self.buff[2],
+            record,
+        }))
+    }
+
+    // read next record bytes to buff, return position of the record
+    fn read_record_bytes(&mut self) -> Result<Option<ErrorContext>, GdsError> {
+        let context = ErrorContext {
+            offset: self.offset,
+            record_index: self.record_count,
+            ..Default::default()
+        };
+        // each gds record first 2 byte stored record byte length
+        let mut len_bytes = [0_u8; 2];
+        let read_len = read_full(&mut self.reader, &mut len_bytes)?;
+        if read_len == 0 {
+            return Ok(None);
+        }
+        if read_len < 2 {
+            return Err(GdsError::Truncated {
+                offset: self.offset,
+            }
+            .in_context(context));
+        }
+
+        let record_len = u16::from_be_bytes(len_bytes) as usize;
+        if record_len < 4 {
+            return Err(GdsError::InvalidRecordData(format!(
+                "not valid gds record length {}",
+                record_len
+            ))
+            .in_context(context));
+        }
+
+        self.buff.clear();
+        self.buff.extend_from_slice(&len_bytes);
+        self.buff.resize(record_len, 0);
+        if read_full(&mut self.reader, &mut self.buff[2..])? < record_len - 2 {
+            return Err(GdsError::Truncated {
+                offset: self.offset,
+            }
+            .in_context(context));
+        }
+
+        self.record_offset = self.offset;
+        self.offset += record_len as u64;
+        self.record_count += 1;
+        Ok(Some(context))
+    }
+}
+
+impl<R: Read> Iterator for RecordReader<R> {
+    type Item = Result<gds_record::Record, GdsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Iterate raw gds records of in-memory gds data without copying
+///
+/// each item is byte offset and bytes of a whole record, including the length prefix
+pub(crate) struct RecordSlices<'a> {
+    bytes: &'a [u8],
+    // byte offset of `bytes` in gds data
+    base: u64,
+    pos: usize,
+    index: usize,
+}
+
+impl<'a> RecordSlices<'a> {
+    /// `base` is byte offset of `bytes` in gds data, `index` is index of first record
+    pub(crate) fn new(bytes: &'a [u8], base: u64, index: usize) -> Self {
+        RecordSlices {
+            bytes,
+            base,
+            pos: 0,
+            index,
+        }
+    }
+
+    /// context of next record
+    pub(crate) fn error_context(&self) -> ErrorContext {
+        ErrorContext {
+            offset: self.base + self.pos as u64,
+            record_index: self.index,
+            ..Default::default()
+        }
+    }
+
+    /// decode next record
+    pub(crate) fn next_record(&mut self) -> Result<Option<gds_record::PosRecord>, GdsError> {
+        let context = self.error_context();
+        let Some((offset, bytes)) = self.next().transpose()? else {
+            return Ok(None);
+        };
+        let record = record_type(bytes).map_err(|e| {
+            e.in_context(ErrorContext {
+                record: gds_record::record_name(bytes[2]),
+                ..context.clone()
+            })
+        })?;
+        Ok(Some(gds_record::PosRecord {
+            offset,
+            index: context.record_index,
+            record_type: bytes[2],
+            record,
+        }))
+    }
+}
+
+impl<'a> Iterator for RecordSlices<'a> {
+    type Item = Result<(u64, &'a [u8]), GdsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.bytes[self.pos..];
+        if rest.is_empty() {
+            return None;
+        }
+        let context = self.error_context();
+        let record_len = match rest {
+            [a, b, ..] => u16::from_be_bytes([*a, *b]) as usize,
+            _ => 0,
+        };
+        let record = match rest.get(..record_len) {
+            Some(record) if record_len >= 4 => record,
+            _ => {
+                // stop iteration after broken record
+                self.pos = self.bytes.len();
+                let err = if record_len > rest.len() || rest.len() < 2 {
+                    GdsError::Truncated {
+                        offset: context.offset,
+                    }
+                } else {
+                    GdsError::InvalidRecordData(format!(
+                        "not valid gds record length {}",
+                        record_len
+                    ))
+                };
+                return Some(Err(err.in_context(context)));
+            }
+        };
+        self.pos += record_len;
+        self.index += 1;
+        Some(Ok((context.offset, record)))
+    }
+}
+
+/// Scan gds records, only record header is read unless record data is asked for
+///
+/// used to index large gds data without decoding all of it
+pub(crate) struct RecordScanner<R: Read + Seek> {
+    reader: R,
+    offset: u64,
+    index: usize,
+    // data bytes of last record not read yet
+    unread: u16,
+}
+
+/// position, length and type of a record found by [`RecordScanner`]
+pub(crate) struct RecordHeader {
+    pub offset: u64,
+    pub index: usize,
+    pub len: u16,
+    pub record_type: [u8; 2],
+}
+
+impl RecordHeader {
+    pub(crate) fn error_context(&self) -> ErrorContext {
+        ErrorContext {
+            offset: self.offset,
+            record_index: self.index,
+            record: gds_record::record_name(self.record_type[0]),
+            ..Default::default()
+        }
+    }
+}
+
+impl<R: Read + Seek> RecordScanner<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        RecordScanner {
+            reader,
+            offset: 0,
+            index: 0,
+            unread: 0,
+        }
+    }
+
+    /// read header of next record, data of previous record is skipped if not read
+    pub(crate) fn next_header(&mut self) -> Result<Option<RecordHeader>, GdsError> {
+        let context = ErrorContext {
+            offset: self.offset,
+            record_index: self.index,
+            ..Default::default()
+        };
+        if self.unread > 0 {
+            self.reader.seek_relative(self.unread as i64)?;
+            self.unread = 0;
+        }
+        let mut header = [0_u8; 4];
+        match read_full(&mut self.reader, &mut header)? {
+            0 => return Ok(None),
+            4 => {}
+            _ => {
+                return Err(GdsError::Truncated {
+                    offset: self.offset,
+                }
+                .in_context(context))
+            }
+        }
+        let len = u16::from_be_bytes([header[0], header[1]]);
+        if len < 4 {
+            return Err(GdsError::InvalidRecordData(format!(
+                "not valid gds record length {}",
+                len
+            ))
+            .in_context(context));
+        }
+        let record_header = RecordHeader {
+            offset: self.offset,
+            index: self.index,
+            len,
+            record_type: [header[2], header[3]],
+        };
+        self.offset += len as u64;
+        self.index += 1;
+        self.unread = len - 4;
+        Ok(Some(record_header))
+    }
+
+    /// read and decode record of header just returned by [`RecordScanner::next_header`]
+    pub(crate) fn read_record(
+        &mut self,
+        header: &RecordHeader,
+    ) -> Result<gds_record::PosRecord, GdsError> {
+        let mut bytes = vec![0_u8; header.len as usize];
+        bytes[..2].copy_from_slice(&header.len.to_be_bytes());
+        bytes[2..4].copy_from_slice(&header.record_type);
+        self.unread = 0;
+        if read_full(&mut self.reader, &mut bytes[4..])? < bytes.len() - 4 {
+            return Err(GdsError::Truncated {
+                offset: header.offset,
+            }
+            .in_context(header.error_context()));
+        }
+        let record = record_type(&bytes).map_err(|e| e.in_context(header.error_context()))?;
+        Ok(gds_record::PosRecord {
+            offset: header.offset,
+            index: header.index,
+            record_type: bytes[2],
+            record,
+        })
+    }
+}
+
+// read until buff is full or stream ends, return read byte count
+fn read_full<R: Read>(reader: &mut R, buff: &mut [u8]) -> std::io::Result<usize> {
+    let mut read_len = 0;
+    while read_len < buff.len() {
+        match reader.read(&mut buff[read_len..]) {
+            Ok(0) => break,
+            Ok(n) => read_len += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(read_len)
+}
+
 #[cfg(test)]
 mod test_gds_reader {
     use super::*;
@@ -349,4 +805,67 @@ mod test_gds_reader {
         byte[1] = 0b00001000_u8;
         assert!(0.5.approx_eq(gdsii_eight_byte_real(&byte).unwrap(), F64Margin::default()));
     }
+
+    #[test]
+    fn test_record_reader() {
+        let bytes: Vec<u8> = vec![
+            0x00, 0x06, 0x00, 0x02, 0x02, 0x58, // HEADER 600
+            0x00, 0x06, 0x0d, 0x02, 0x00, 0x05, // LAYER 5
+            0x00, 0x04, 0x04, 0x00, // ENDLIB
+        ];
+        let mut reader = RecordReader::new(bytes.as_slice());
+        assert!(matches!(
+            reader.read_record().unwrap(),
+            Some(gds_record::Record::Header { version: 600 })
+        ));
+        assert!(matches!(
+            reader.read_record().unwrap(),
+            Some(gds_record::Record::Layer(5))
+        ));
+        assert_eq!(reader.record_offset(), 6);
+        assert!(matches!(
+            reader.read_record().unwrap(),
+            Some(gds_record::Record::EndLib)
+        ));
+        assert!(reader.read_record().unwrap().is_none());
+
+        // record data shorter than record length
+        let truncated: Vec<u8> = vec![0x00, 0x06, 0x00, 0x02, 0x02];
+        let mut reader = RecordReader::new(truncated.as_slice());
+        assert!(matches!(
+            reader.next().unwrap().unwrap_err().root(),
+            GdsError::Truncated { offset: 0 }
+        ));
+        assert!(reader.next().is_none());
+
+        // TEXTNODE record is not decoded but kept
+        let unknown: Vec<u8> = vec![
+            0x00, 0x06, 0x0d, 0x02, 0x00, 0x05, 0x00, 0x06, 0x14, 0x00, 0xab, 0xcd, 0x00, 0x04,
+            0x50, 0x00,
+        ];
+        let mut reader = RecordReader::new(unknown.as_slice());
+        assert!(reader.next().unwrap().is_ok());
+        let record = reader.next().unwrap().unwrap();
+        assert_eq!(record.name(), "TEXTNODE");
+        assert!(matches!(
+            record,
+            gds_record::Record::Unknown {
+                record_type: 0x14,
+                data_type: 0x00,
+                bytes
+            } if bytes == [0xab, 0xcd]
+        ));
+        // record type not defined by gds manual is kept too
+        let record = reader.next().unwrap().unwrap();
+        assert_eq!(record.name(), "UNKNOWN");
+        assert!(matches!(
+            record,
+            gds_record::Record::Unknown {
+                record_type: 0x50,
+                data_type: 0x00,
+                bytes
+            } if bytes.is_empty()
+        ));
+        assert!(reader.next().is_none());
+    }
 }
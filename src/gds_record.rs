@@ -58,8 +58,8 @@ pub const PROPVALUE: &[u8] = &[0x2c, 0x06];
 pub const BOX: &[u8] = &[0x2d, 0x00];
 pub const BOXTYPE: &[u8] = &[0x2e, 0x02];
 pub const PLEX: &[u8] = &[0x2f, 0x03];
-pub const BGNEXTN: &[u8] = &[0x30, 0x03]; //TODO:
-pub const ENDEXTN: &[u8] = &[0x31, 0x03]; // TODO:
+pub const BGNEXTN: &[u8] = &[0x30, 0x03];
+pub const ENDEXTN: &[u8] = &[0x31, 0x03];
 pub const TAPENUM: &[u8] = &[0x32, 0x03];
 pub const TAPECODE: &[u8] = &[0x33, 0x02];
 pub const STRCLASS: &[u8] = &[0x34, 0x01];
@@ -81,6 +81,85 @@ pub const USERCONSTRAINT: &[u8] = &[0x43, 0x00];
 pub const SPACERERROR: &[u8] = &[0x44, 0x00];
 pub const CONTACT: &[u8] = &[0x45, 0x00];
 
+// record names indexed by record type
+const RECORD_NAMES: [&str; 0x46] = [
+    "HEADER",
+    "BGNLIB",
+    "LIBNAME",
+    "UNITS",
+    "ENDLIB",
+    "BGNSTR",
+    "STRNAME",
+    "ENDSTR",
+    "BOUNDARY",
+    "PATH",
+    "SREF",
+    "AREF",
+    "TEXT",
+    "LAYER",
+    "DATATYPE",
+    "WIDTH",
+    "XY",
+    "ENDEL",
+    "SNAME",
+    "COLROW",
+    "TEXTNODE",
+    "NODE",
+    "TEXTTYPE",
+    "PRESENTATION",
+    "SPACING",
+    "STRING",
+    "STRANS",
+    "MAG",
+    "ANGLE",
+    "UINTEGER",
+    "USTRING",
+    "REFLIBS",
+    "FONTS",
+    "PATHTYPE",
+    "GENERATIONS",
+    "ATTRTABLE",
+    "STYPTABLE",
+    "STRTYPE",
+    "ELFLAGS",
+    "ELKEY",
+    "LINKTYPE",
+    "LINKKEYS",
+    "NODETYPE",
+    "PROPATTR",
+    "PROPVALUE",
+    "BOX",
+    "BOXTYPE",
+    "PLEX",
+    "BGNEXTN",
+    "ENDEXTN",
+    "TAPENUM",
+    "TAPECODE",
+    "STRCLASS",
+    "RESERVED",
+    "FORMAT",
+    "MASK",
+    "ENDMASKS",
+    "LIBDIRSIZE",
+    "SRFNAME",
+    "LIBSECUR",
+    "BORDER",
+    "SOFTFENCE",
+    "HARDFENCE",
+    "SOFTWIRE",
+    "HARDWIRE",
+    "PATHPORT",
+    "NODEPORT",
+    "USERCONSTRAINT",
+    "SPACERERROR",
+    "CONTACT",
+];
+
+/// gds record name of record type, None if record type is not defined by gds spec
+pub fn record_name(record_type: u8) -> Option<&'static str> {
+    RECORD_NAMES.get(record_type as usize).copied()
+}
+
 #[derive(Debug)]
 pub enum PresentationFont {
     Fonts0,
@@ -134,7 +213,7 @@ pub enum Record {
         row: i16,
     },
     // TEXTNODE,
-    // NODE,
+    Node,
     TextType(i16),
     Presentation {
         font_num: PresentationFont,
@@ -152,34 +231,34 @@ pub enum Record {
     Angle(f64),
     // UINTEGER,
     // USTRING,
-    // REFLIBS,
-    // FONTS,
+    RefLibs(Vec<String>),
+    Fonts(Vec<String>),
     PathType(i16),
-    // GENERATIONS,
-    // ATTRTABLE,
+    Generations(i16),
+    AttrTable(String),
     // STYPTABLE,
     // STRTYPE,
-    // ELFLAGS,
+    ElFlags(u16),
     // ELKEY,
     // LINKTYPE,
     // LINKKEYS,
-    // NODETYPE,
+    NodeType(i16),
     PropAttr(i16),
     PropValue(String),
     Box,
     BoxType(i16),
-    // PLEX,
-    // BGNEXTN,
-    // ENDEXTN,
+    Plex(i32),
+    BgnExtn(i32),
+    EndExtn(i32),
     // TAPENUM,
     // TAPECODE,
-    // STRCLASS,
+    StrClass(u16),
     // RESERVED,
-    // FORMAT,
-    // MASK,
-    // ENDMASKS,
+    Format(i16),
+    Mask(String),
+    EndMasks,
     // LIBDIRSIZE,
-    // SRFNAME,
+    SrfName(String),
     // LIBSECUR,
     // BORDER,
     // SOFTFENCE,
@@ -191,4 +270,86 @@ pub enum Record {
     // USERCONSTRAINT,
     // SPACERERROR,
     // CONTACT,
+    /// record not decoded by gdsdk, `bytes` is record data without length and type
+    Unknown {
+        record_type: u8,
+        data_type: u8,
+        bytes: Vec<u8>,
+    },
+}
+
+impl Record {
+    /// gds record name, like `XY`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Record::Header { .. } => "HEADER",
+            Record::BgnLib(_) => "BGNLIB",
+            Record::LibName(_) => "LIBNAME",
+            Record::Units { .. } => "UNITS",
+            Record::EndLib => "ENDLIB",
+            Record::BgnStr(_) => "BGNSTR",
+            Record::StrName(_) => "STRNAME",
+            Record::EndStr => "ENDSTR",
+            Record::Boundary => "BOUNDARY",
+            Record::Path => "PATH",
+            Record::StrRef => "SREF",
+            Record::AryRef => "AREF",
+            Record::Text => "TEXT",
+            Record::Layer(_) => "LAYER",
+            Record::DataType(_) => "DATATYPE",
+            Record::Width(_) => "WIDTH",
+            Record::Points(_) => "XY",
+            Record::EndElem => "ENDEL",
+            Record::StrRefName(_) => "SNAME",
+            Record::ColRow { .. } => "COLROW",
+            Record::TextType(_) => "TEXTTYPE",
+            Record::Presentation { .. } => "PRESENTATION",
+            Record::String(_) => "STRING",
+            Record::RefTrans { .. } => "STRANS",
+            Record::Mag(_) => "MAG",
+            Record::Angle(_) => "ANGLE",
+            Record::RefLibs(_) => "REFLIBS",
+            Record::Fonts(_) => "FONTS",
+            Record::PathType(_) => "PATHTYPE",
+            Record::Generations(_) => "GENERATIONS",
+            Record::AttrTable(_) => "ATTRTABLE",
+            Record::PropAttr(_) => "PROPATTR",
+            Record::PropValue(_) => "PROPVALUE",
+            Record::Box => "BOX",
+            Record::BoxType(_) => "BOXTYPE",
+            Record::Node => "NODE",
+            Record::NodeType(_) => "NODETYPE",
+            Record::ElFlags(_) => "ELFLAGS",
+            Record::Plex(_) => "PLEX",
+            Record::BgnExtn(_) => "BGNEXTN",
+            Record::EndExtn(_) => "ENDEXTN",
+            Record::StrClass(_) => "STRCLASS",
+            Record::Format(_) => "FORMAT",
+            Record::Mask(_) => "MASK",
+            Record::EndMasks => "ENDMASKS",
+            Record::SrfName(_) => "SRFNAME",
+            Record::Unknown { record_type, .. } => record_name(*record_type).unwrap_or("UNKNOWN"),
+        }
+    }
+}
+
+// gds record with its position in gds data
+#[derive(Debug)]
+pub(crate) struct PosRecord {
+    pub offset: u64,
+    pub index: usize,
+    // record type byte, also of decoded record
+    pub record_type: u8,
+    pub record: Record,
+}
+
+impl PosRecord {
+    pub(crate) fn error_context(&self) -> gds_error::ErrorContext {
+        gds_error::ErrorContext {
+            offset: self.offset,
+            record_index: self.index,
+            record: Some(self.record.name()),
+            ..Default::default()
+        }
+    }
 }
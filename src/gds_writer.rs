@@ -1,41 +1,330 @@
+use chrono::{DateTime, NaiveDateTime};
+use std::io::Write;
 use std::vec::Vec;
 
 use super::gds_model;
+use crate::gds_compress::{Compression, Encoder};
+use crate::gds_error::GdsError;
+use crate::gds_model::{Element, GdsObject, RawRecordWriter};
+use crate::gds_record;
 
-pub(crate) fn ascii_string_to_be_bytes(s: &str) -> Vec<u8> {
+/// Options of writing gds data
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    /// compress written gds data, default is no compression
+    pub compression: Compression,
+    /// most points in XY record of polygons and paths, closing point of polygon is counted,
+    /// like 200, 600 or 8191 of different tools, can not exceed 8191 of XY record size
+    ///
+    /// default is 8191
+    pub max_points: usize,
+    /// split polygons and paths with more points than `max_points` into several elements,
+    /// otherwise they are rejected with error
+    ///
+    /// polygons are cut along vertical or horizontal lines, paths are cut at vertices
+    ///
+    /// default is false
+    pub split_large: bool,
+    /// order of structures written by `Lib`, default is insertion order
+    pub struc_order: StrucOrder,
+    /// dates written in BGNLIB and BGNSTR records, default is dates of lib and structures
+    pub timestamps: Timestamps,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            compression: Compression::None,
+            max_points: gds_model::MAX_XY_POINTS,
+            split_large: false,
+            struc_order: StrucOrder::Insertion,
+            timestamps: Timestamps::Preserve,
+        }
+    }
+}
+
+/// Order of structures in written gds data, same lib is always written to same bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StrucOrder {
+    /// order of adding structures to lib, refered structures are added after referring one
+    #[default]
+    Insertion,
+    /// sorted by structure name
+    Alphabetical,
+    /// refered structures are written before structures referring them
+    BottomUp,
+}
+
+/// Dates written in BGNLIB and BGNSTR records, fixed or zero dates make written data reproducible
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Timestamps {
+    /// dates of lib and structures
+    #[default]
+    Preserve,
+    /// all dates are zero
+    Zero,
+    /// modification and access time of lib and structures are given time
+    Fixed(NaiveDateTime),
+}
+
+impl Timestamps {
+    /// fixed time of `SOURCE_DATE_EPOCH` environment variable, in seconds since unix epoch
+    ///
+    /// dates are preserved if it is not set
+    pub fn source_date_epoch() -> Result<Self, GdsError> {
+        match std::env::var("SOURCE_DATE_EPOCH") {
+            Ok(epoch) => epoch_timestamps(&epoch),
+            Err(_) => Ok(Timestamps::Preserve),
+        }
+    }
+
+    // date written for date of lib or structure
+    pub(crate) fn date(&self, date: &gds_model::Date) -> Result<gds_model::Date, GdsError> {
+        match self {
+            Timestamps::Preserve => Ok(date.clone()),
+            Timestamps::Zero => Ok(gds_model::Date::new()),
+            Timestamps::Fixed(time) => gds_model::Date::from_naive(time),
+        }
+    }
+}
+
+fn epoch_timestamps(epoch: &str) -> Result<Timestamps, GdsError> {
+    epoch
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+        .map(|time| Timestamps::Fixed(time.naive_utc()))
+        .ok_or_else(|| GdsError::ValueOutOfRange(format!("not valid SOURCE_DATE_EPOCH: {}", epoch)))
+}
+
+impl WriteOptions {
+    // points limit of XY record
+    pub(crate) fn points_limit(&self) -> usize {
+        self.max_points.min(gds_model::MAX_XY_POINTS)
+    }
+}
+
+/// Write gds data to byte stream structure by structure, element by element
+///
+/// no [`Lib`](gds_model::Lib) holding all structures is needed, so cells can be generated and
+/// written incrementally. references are written by name of referred structure and not checked
+pub struct GdsStreamWriter<W: Write> {
+    writer: Encoder<W>,
+    // raw records of lib, put after header records and structures
+    raw_writer: RawRecordWriter,
+    scaling: f64,
+    release: i16,
+    options: WriteOptions,
+    // name of structure being written
+    current_struct: Option<String>,
+}
+
+impl<W: Write> GdsStreamWriter<W> {
+    /// write lib records from HEADER to UNITS of `lib`, structures of `lib` are not written
+    pub fn new(writer: W, lib: &gds_model::Lib, options: &WriteOptions) -> Result<Self, GdsError> {
+        let release = gds_model::gds_release(lib.version)?;
+        lib.check_header_release(release)?;
+        let mut stream = GdsStreamWriter {
+            writer: Encoder::new(writer, options.compression)?,
+            raw_writer: RawRecordWriter::new(&lib.raw_records),
+            scaling: lib.units / lib.precision,
+            release,
+            options: options.clone(),
+            current_struct: None,
+        };
+        for record in gds_model::split_records(&lib.header_gds(&options.timestamps)?) {
+            stream.raw_writer.record(&mut stream.writer, record)?;
+        }
+        Ok(stream)
+    }
+
+    /// write BGNSTR and STRNAME of a new structure
+    pub fn begin_struct(&mut self, name: &str) -> Result<(), GdsError> {
+        self.check_in_struct("BGNSTR", false)?;
+        let header = gds_model::Struc::new(name).header_gds(&self.options.timestamps)?;
+        self.writer.write_all(&header)?;
+        self.current_struct = Some(name.to_string());
+        Ok(())
+    }
+
+    /// write polygon, split if it has more points than limit of options
+    pub fn polygon(&mut self, polygon: &gds_model::Polygon) -> Result<(), GdsError> {
+        self.element("BOUNDARY", Element::Polygon(polygon))
+    }
+
+    /// write path, split if it has more points than limit of options
+    pub fn path(&mut self, path: &gds_model::Path) -> Result<(), GdsError> {
+        self.element("PATH", Element::Path(path))
+    }
+
+    /// write SREF, or AREF if `sref` has rows or columns
+    pub fn sref(&mut self, sref: &gds_model::Ref) -> Result<(), GdsError> {
+        self.element("SREF", Element::Ref(sref))
+    }
+
+    pub fn text(&mut self, text: &gds_model::Text) -> Result<(), GdsError> {
+        self.element("TEXT", Element::Text(text))
+    }
+
+    pub fn node(&mut self, node: &gds_model::Node) -> Result<(), GdsError> {
+        self.element("NODE", Element::Node(node))
+    }
+
+    pub fn gds_box(&mut self, gds_box: &gds_model::GdsBox) -> Result<(), GdsError> {
+        self.element("BOX", Element::Box(gds_box))
+    }
+
+    /// write ENDSTR of current structure
+    pub fn end_struct(&mut self) -> Result<(), GdsError> {
+        self.check_in_struct("ENDSTR", true)?;
+        self.writer.write_all(&4_i16.to_be_bytes())?;
+        self.writer.write_all(gds_record::ENDSTR)?;
+        if let Some(name) = self.current_struct.take() {
+            self.raw_writer
+                .after(&mut self.writer, &gds_model::RawAnchor::Struc(name))?;
+        }
+        Ok(())
+    }
+
+    /// write a whole structure, structures it refers to are not written
+    pub fn struc(&mut self, struc: &gds_model::Struc) -> Result<(), GdsError> {
+        self.check_in_struct("BGNSTR", false)?;
+        struc.check_release(self.release)?;
+        struc.write_to(&mut self.writer, self.scaling, &self.options)?;
+        self.raw_writer.after(
+            &mut self.writer,
+            &gds_model::RawAnchor::Struc(struc.name.clone()),
+        )
+    }
+
+    /// write ENDLIB and end of compressed stream, return inner writer
+    pub fn finish(mut self) -> Result<W, GdsError> {
+        self.check_in_struct("ENDLIB", false)?;
+        let mut endlib = Vec::<u8>::new();
+        endlib.extend(4_i16.to_be_bytes());
+        endlib.extend(gds_record::ENDLIB);
+        self.raw_writer.last_record(&mut self.writer, &endlib)?;
+        self.writer.finish()
+    }
+
+    fn element(&mut self, record: &str, element: Element) -> Result<(), GdsError> {
+        self.check_in_struct(record, true)?;
+        element.check_release(self.release)?;
+        let data = match element {
+            Element::Polygon(e) => e.to_gds_limited(self.scaling, &self.options)?,
+            Element::Path(e) => e.to_gds_limited(self.scaling, &self.options)?,
+            Element::Ref(e) => e.to_gds(self.scaling)?,
+            Element::Text(e) => e.to_gds(self.scaling)?,
+            Element::Node(e) => e.to_gds(self.scaling)?,
+            Element::Box(e) => e.to_gds(self.scaling)?,
+        };
+        self.writer.write_all(&data)?;
+        Ok(())
+    }
+
+    fn check_in_struct(&self, record: &str, in_struct: bool) -> Result<(), GdsError> {
+        if self.current_struct.is_some() == in_struct {
+            return Ok(());
+        }
+        Err(GdsError::InvalidRecordData(if in_struct {
+            format!("{} record can only be written in structure", record)
+        } else {
+            format!("{} record can not be written in structure", record)
+        }))
+    }
+}
+
+pub(crate) fn ascii_string_to_be_bytes(s: &str) -> Result<Vec<u8>, GdsError> {
     let mut be_bytes = Vec::<u8>::new();
     if !s.is_ascii() {
-        panic!("{} is not ascii", s);
+        return Err(GdsError::ValueOutOfRange(format!("{} is not ascii", s)));
     }
     for c in s.chars() {
         be_bytes.push(c as u8);
     }
 
-    be_bytes
+    Ok(be_bytes)
 }
 
-pub(crate) fn f64_to_gds_bytes(v: f64) -> Vec<u8> {
-    let mut be_bytes = Vec::<u8>::new();
-    be_bytes.resize(1, 0);
+/// convert IEEE 754 f64 to gdsii eight byte real, see
+/// [`gdsii_eight_byte_real`](crate::gds_reader::gdsii_eight_byte_real) for the format
+///
+/// value = (-1)^S*M*2^(4*E-312) with 56 bit M, so every f64 of gds range
+/// 2^-260 ~ 2^252 is written exactly with normalized M, first hex digit of M is not 0.
+/// smaller values are written with E = 0 and M rounded half to even, NaN, infinite
+/// and values out of range are error
+pub(crate) fn f64_to_gds_bytes(v: f64) -> Result<Vec<u8>, GdsError> {
+    if !v.is_finite() {
+        return Err(GdsError::ValueOutOfRange(format!(
+            "Gds eight byte real can not be {}",
+            v
+        )));
+    }
+    let bits = v.to_bits();
+    let sign = ((bits >> 63) as u8) << 7;
+    let biased = ((bits >> 52) & 0x7ff) as i32;
+    let fraction = bits & ((1 << 52) - 1);
+    // v = mantissa*2^exp2
+    let (mantissa, exp2) = if biased == 0 {
+        (fraction, -1074)
+    } else {
+        (fraction | (1 << 52), biased - 1075)
+    };
+    if mantissa == 0 {
+        let mut be_bytes = vec![0; 8];
+        be_bytes[0] = sign;
+        return Ok(be_bytes);
+    }
 
-    // sign
-    be_bytes[0] |= (v.is_sign_negative() as u8).to_be_bytes()[0];
-    // exponent
-    let fexp = 0.25 * v.log2();
-    let mut exponent = fexp.ceil();
-    if exponent == fexp {
-        exponent = exponent + 1_f64;
+    // highest bit of v is 2^top, put it at bit 52..55 of gds mantissa
+    let top = 63 - mantissa.leading_zeros() as i32 + exp2;
+    let mut exponent = (top + 260).div_euclid(4).max(0);
+    let shift = exp2 - (4 * exponent - 312);
+    let mut gds_mantissa = if shift >= 0 {
+        mantissa << shift
+    } else {
+        round_shift_right(mantissa, (-shift) as u32)
+    };
+    // rounding carried to bit 56
+    if gds_mantissa >> 56 != 0 {
+        gds_mantissa >>= 4;
+        exponent += 1;
+    }
+    if exponent > 0x7f {
+        return Err(GdsError::ValueOutOfRange(format!(
+            "Gds eight byte real can not exceed 16^63: {}",
+            v
+        )));
     }
 
-    // mantissa
-    let mantissa = v * 16_f64.powf(14_f64 - exponent);
-    let mantissa_byte = (mantissa as u64).to_be_bytes();
+    let mut be_bytes = gds_mantissa.to_be_bytes().to_vec();
+    be_bytes[0] = sign | (exponent as u8 & 0x7f);
+    Ok(be_bytes)
+}
 
-    // assemble binary
-    be_bytes[0] |= ((exponent + 64_f64) as u8).to_be_bytes()[0];
-    be_bytes.extend(&mantissa_byte[1..]);
+// value >> shift, rounded half to even
+fn round_shift_right(value: u64, shift: u32) -> u64 {
+    if shift >= 64 {
+        return 0;
+    }
+    let kept = value >> shift;
+    let rest = value - (kept << shift);
+    let half = 1_u64 << (shift - 1);
+    if rest > half || (rest == half && kept & 1 == 1) {
+        kept + 1
+    } else {
+        kept
+    }
+}
 
-    be_bytes
+pub(crate) fn text_font_to_gds_num(font: &gds_model::TextFont) -> u16 {
+    match font {
+        gds_model::TextFont::Fonts0 => 0,
+        gds_model::TextFont::Fonts1 => 1,
+        gds_model::TextFont::Fonts2 => 2,
+        gds_model::TextFont::Fonts3 => 3,
+    }
 }
 
 pub(crate) fn text_anchor_to_gds_num(anchor: &gds_model::TextAnchor) -> u16 {
@@ -63,8 +352,228 @@ mod test_gds_writer {
     fn test_f64_to_gds_bytes() {
         let v = 1.0e-9;
 
-        let gds_be_bytes = f64_to_gds_bytes(v);
+        let gds_be_bytes = f64_to_gds_bytes(v).unwrap();
         let fv = gds_reader::gdsii_eight_byte_real(&gds_be_bytes).unwrap();
         assert!(v.approx_eq(fv, F64Margin::default()));
+
+        let bytes = |v: f64| u64::from_be_bytes(f64_to_gds_bytes(v).unwrap().try_into().unwrap());
+        assert_eq!(bytes(0.0), 0);
+        assert_eq!(bytes(-0.0), 0x8000_0000_0000_0000);
+        assert_eq!(bytes(1.0), 0x4110_0000_0000_0000);
+        assert_eq!(bytes(-1.0), 0xc110_0000_0000_0000);
+        assert_eq!(bytes(0.5), 0x4080_0000_0000_0000);
+        assert_eq!(bytes(-90.0), 0xc25a_0000_0000_0000);
+        // largest and smallest normalized values
+        assert_eq!(
+            bytes(2_f64.powi(252) - 2_f64.powi(199)),
+            0x7fff_ffff_ffff_fff8
+        );
+        assert_eq!(bytes(2_f64.powi(-260)), 0x0010_0000_0000_0000);
+        assert!(f64_to_gds_bytes(2_f64.powi(252)).is_err());
+        assert!(f64_to_gds_bytes(-1e100).is_err());
+        assert!(f64_to_gds_bytes(f64::NAN).is_err());
+        assert!(f64_to_gds_bytes(f64::INFINITY).is_err());
+
+        // below gds range, mantissa of E = 0 is rounded half to even
+        let unit = 2_f64.powi(-312);
+        assert_eq!(bytes(unit * 0.5), 0);
+        assert_eq!(bytes(-unit * 0.75), 0x8000_0000_0000_0001);
+        assert_eq!(bytes(unit * 1.5), 2);
+        assert_eq!(bytes(unit * 2.5), 2);
+        assert_eq!(bytes(unit * 3.0), 3);
+        assert_eq!(bytes(f64::MIN_POSITIVE), 0);
+        assert_eq!(bytes(5e-324), 0);
+    }
+
+    // xorshift random numbers, same sequence on every run
+    fn random_u64s(count: usize) -> impl Iterator<Item = u64> {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        (0..count).map(move |_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        })
+    }
+
+    #[test]
+    fn test_f64_gds_real_round_trip() {
+        let min = 2_f64.powi(-260);
+        let max = 2_f64.powi(252);
+        // random f64 bits, and values of every binary exponent in gds range
+        let values = random_u64s(1 << 16)
+            .map(f64::from_bits)
+            .chain((-260..252).flat_map(|e| {
+                let v = 2_f64.powi(e);
+                [v, -v, v * (2.0 - f64::EPSILON), v * 1.1, v * 1.9]
+            }));
+        for v in values {
+            match f64_to_gds_bytes(v) {
+                Ok(gds_bytes) => {
+                    let read = gds_reader::gdsii_eight_byte_real(&gds_bytes).unwrap();
+                    if v.abs() >= min || v == 0.0 {
+                        // gds mantissa of normalized value holds all bits of f64
+                        assert_eq!(read.to_bits(), v.to_bits(), "{:e}", v);
+                        assert_ne!(gds_bytes[1] & 0xf0, 0, "{:e}", v);
+                    } else {
+                        assert!((read - v).abs() <= 2_f64.powi(-313), "{:e}", v);
+                        assert_eq!(gds_bytes[0] & 0x7f, 0);
+                    }
+                }
+                Err(_) => assert!(!v.is_finite() || v.abs() >= max, "{:e}", v),
+            }
+        }
+    }
+
+    #[test]
+    fn test_gds_real_f64_round_trip() {
+        // random gds reals of every exponent and sign, read values are written back to same value
+        let mut randoms = random_u64s(1 << 17);
+        for sign_exponent in 0..=0xff_u64 {
+            for _ in 0..256 {
+                let mantissa = (randoms.next().unwrap() >> 8) >> (randoms.next().unwrap() % 56);
+                let bits = sign_exponent << 56 | mantissa;
+                let gds_bytes = bits.to_be_bytes();
+                let read = gds_reader::gdsii_eight_byte_real(&gds_bytes).unwrap();
+                let written = f64_to_gds_bytes(read).unwrap();
+                let read_back = gds_reader::gdsii_eight_byte_real(&written).unwrap();
+                assert_eq!(read_back.to_bits(), read.to_bits(), "{:#x}", bits);
+
+                // bytes are same if mantissa is normalized and fits in f64
+                let exponent = sign_exponent & 0x7f;
+                let normalized = mantissa >> 52 != 0 || exponent == 0;
+                if mantissa != 0 && normalized && (mantissa as f64) as u64 == mantissa {
+                    assert_eq!(written, gds_bytes, "{:#x}", bits);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_gds_real_round_trip() {
+        // every eight byte real of sample files is written back to same bytes
+        let sample = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("sample");
+        let mut count = 0;
+        for entry in std::fs::read_dir(sample).unwrap() {
+            let data = std::fs::read(entry.unwrap().path()).unwrap();
+            let mut pos = 0;
+            while pos + 4 <= data.len() {
+                let len = u16::from_be_bytes([data[pos], data[pos + 1]]) as usize;
+                if len < 4 {
+                    break;
+                }
+                // data type 5 is eight byte real
+                if data[pos + 3] == 5 {
+                    for real in data[pos + 4..pos + len].chunks(8) {
+                        let value = gds_reader::gdsii_eight_byte_real(real).unwrap();
+                        let written = f64_to_gds_bytes(value).unwrap();
+                        let read_back = gds_reader::gdsii_eight_byte_real(&written).unwrap();
+                        assert_eq!(read_back.to_bits(), value.to_bits());
+                        let mantissa =
+                            u64::from_be_bytes(real.try_into().unwrap()) & 0x00ff_ffff_ffff_ffff;
+                        if (mantissa as f64) as u64 == mantissa {
+                            assert_eq!(written, real);
+                        }
+                        count += 1;
+                    }
+                }
+                pos += len;
+            }
+        }
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn test_stream_writer() {
+        use crate::gds_model::*;
+        use std::sync::{Arc, RwLock};
+
+        let header = Lib::new("stream");
+        let mut stream =
+            GdsStreamWriter::new(Vec::new(), &header, &WriteOptions::default()).unwrap();
+        let cell = Arc::new(RwLock::new(Struc::new("cell")));
+        stream.begin_struct("cell").unwrap();
+        for i in 0..3 {
+            let polygon = Polygon {
+                points: vec![
+                    Points::new(i as f64, 0.0),
+                    Points::new(i as f64 + 1.0, 0.0),
+                    Points::new(i as f64, 1.0),
+                ],
+                ..Default::default()
+            };
+            stream.polygon(&polygon).unwrap();
+        }
+        stream.end_struct().unwrap();
+        stream.begin_struct("top").unwrap();
+        // element can not be written outside structure
+        assert!(stream.end_struct().is_ok());
+        assert!(stream.sref(&Ref::new(&cell)).is_err());
+        let mut top = Struc::new("top2");
+        top.refs.push(Ref::new(&cell));
+        stream.struc(&top).unwrap();
+        let bytes = stream.finish().unwrap();
+
+        let lib = crate::read_gdsii_from(bytes.as_slice()).unwrap();
+        assert_eq!(lib.name, "stream");
+        let strucs = lib.all_strucs();
+        assert_eq!(strucs.len(), 3);
+        let cell = strucs.iter().find(|s| s.read().unwrap().name == "cell");
+        assert_eq!(cell.unwrap().read().unwrap().polygons.len(), 3);
+        let top = lib.top_strucs();
+        assert_eq!(top.len(), 2);
+
+        // lib written to stream is same as its gds bytes
+        let mut written = Vec::new();
+        lib.write_to(&mut written, &WriteOptions::default())
+            .unwrap();
+        assert_eq!(written, lib.gds_bytes().unwrap());
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let fixed = epoch_timestamps("1700000000").unwrap();
+        let Timestamps::Fixed(time) = fixed else {
+            panic!("{:?}", fixed);
+        };
+        assert_eq!(time.to_string(), "2023-11-14 22:13:20");
+        assert!(epoch_timestamps("not a number").is_err());
+
+        let mut lib = gds_model::Lib::new("test");
+        let cell = std::sync::Arc::new(std::sync::RwLock::new(gds_model::Struc::new("cell")));
+        lib.add_struc(&cell).unwrap();
+        let write = |lib: &gds_model::Lib, timestamps| {
+            let mut data = Vec::new();
+            let options = WriteOptions {
+                timestamps,
+                ..Default::default()
+            };
+            lib.write_to(&mut data, &options).unwrap();
+            data
+        };
+
+        // fixed and zero dates do not depend on dates of lib and structures
+        let fixed_bytes = write(&lib, fixed);
+        let zero_bytes = write(&lib, Timestamps::Zero);
+        lib.date.mod_year -= 1;
+        cell.write().unwrap().date.acc_day = 0;
+        assert_eq!(write(&lib, fixed), fixed_bytes);
+        assert_eq!(write(&lib, Timestamps::Zero), zero_bytes);
+
+        let read_lib = crate::read_gdsii_from(fixed_bytes.as_slice()).unwrap();
+        assert_eq!(read_lib.date.modification().unwrap(), time);
+        assert_eq!(read_lib.date.access().unwrap(), time);
+        let read_cell = read_lib.all_strucs()[0].clone();
+        assert_eq!(read_cell.read().unwrap().date.modification().unwrap(), time);
+
+        let read_lib = crate::read_gdsii_from(zero_bytes.as_slice()).unwrap();
+        assert!(read_lib.date.modification().is_err());
+
+        // original dates are preserved
+        let read_lib =
+            crate::read_gdsii_from(write(&lib, Timestamps::Preserve).as_slice()).unwrap();
+        assert_eq!(read_lib.date.mod_year, lib.date.mod_year);
+        let read_cell = read_lib.all_strucs()[0].clone();
+        assert_eq!(read_cell.read().unwrap().date.acc_day, 0);
     }
 }
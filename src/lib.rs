@@ -4,82 +4,105 @@
 //!
 //! Or create gds object and export to file
 
-#[macro_use]
+mod gds_compress;
+mod gds_diagnostic;
 pub mod gds_error;
+mod gds_lazy;
 pub mod gds_model;
 mod gds_parser;
 mod gds_reader;
-mod gds_record;
+pub mod gds_record;
 mod gds_writer;
 
-use rayon::prelude::*;
-use std::error::Error;
-use std::fs::read;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
 use std::path;
 
-fn to_gds_record(
-    buff: &[u8],
-    (start, end): &(usize, usize),
-) -> Result<gds_record::Record, Box<dyn Error>> {
-    Ok(gds_reader::record_type(&buff[*start..*end]).unwrap())
-}
+pub use gds_compress::Compression;
+pub use gds_diagnostic::{Diagnostic, DiagnosticSink, LogSink, Severity};
+pub use gds_error::{ErrorContext, GdsError, GdsResult};
+pub use gds_lazy::{read_gdsii_lazy, read_gdsii_mmap, LazyLib};
+pub use gds_reader::{ReadOptions, RecordReader};
+pub use gds_writer::{GdsStreamWriter, StrucOrder, Timestamps, WriteOptions};
 
 /// read gds file return gds lib
-pub fn read_gdsii<T: AsRef<path::Path>>(
+pub fn read_gdsii<T: AsRef<path::Path>>(gds_file: T) -> Result<Box<gds_model::Lib>, GdsError> {
+    read_gdsii_from(File::open(gds_file)?)
+}
+
+/// read gds lib from any byte stream, like pipe, socket or decompressor
+///
+/// records are decoded one by one, whole stream is never staged in memory,
+/// compressed data is detected by magic bytes and decompressed on the fly
+pub fn read_gdsii_from<R: Read>(reader: R) -> Result<Box<gds_model::Lib>, GdsError> {
+    let (lib, _) = read_gdsii_from_with_options(reader, &ReadOptions::default())?;
+    Ok(lib)
+}
+
+/// read gds file with options, return gds lib and diagnostics, like anomalies skipped in non-strict mode
+pub fn read_gdsii_with_options<T: AsRef<path::Path>>(
     gds_file: T,
-) -> Result<Box<gds_model::Lib>, Box<dyn Error + Sync + Send>> {
-    let buff = read(gds_file)?;
-    let byte_len = buff.len();
-    if byte_len < 4usize {
-        return Result::Err(Box::new(gds_err!(
-            "not valid gds file, file size less than 4 byte"
-        )));
-    }
+    options: &ReadOptions,
+) -> Result<(Box<gds_model::Lib>, Vec<Diagnostic>), GdsError> {
+    read_gdsii_from_with_options(File::open(gds_file)?, options)
+}
 
-    // check valid gds file
-    if let gds_record::HEADER = &buff[2..4] {
-        // do nothing
-    } else {
-        return Result::Err(Box::new(gds_err!(
-            "not valid gds file, no valid gds header section found"
-        )));
+/// read gds lib from any byte stream with options, return gds lib and diagnostics
+///
+/// diagnostics are also sent to `options.sink` in offset order, even if reading fails
+pub fn read_gdsii_from_with_options<R: Read>(
+    reader: R,
+    options: &ReadOptions,
+) -> Result<(Box<gds_model::Lib>, Vec<Diagnostic>), GdsError> {
+    let mut reader = RecordReader::new(gds_compress::decompress(reader)?);
+    // transfer gds record data to gds object
+    let (lib, mut diagnostics) = gds_parser::parse_gds(reader.pos_records(), options);
+    let lib = lib.and_then(|lib| {
+        check_trailing_data(&mut reader, options, &mut diagnostics)?;
+        Ok(lib)
+    });
+    for diagnostic in &diagnostics {
+        options.sink.report(diagnostic);
     }
+    Ok((lib?, diagnostics))
+}
 
-    let mut idx: usize = 0;
-    let mut record_ranges = Vec::new();
-    while idx < buff.len() {
-        // each gds record first 2 byte stored record byte length
-        let record_len = u16::from_be_bytes(match buff[idx..idx + 2].try_into() {
-            Ok(v) => v,
-            Err(err) => {
-                return Result::Err(Box::new(gds_err!(&format!(
-                    "transfer gds record failed {}",
-                    err
-                ))))
-            }
-        }) as usize;
-
-        if record_len == 0 {
-            return Result::Err(Box::new(gds_err!(
-                "not valid gds record length, zero length"
-            )));
+// null bytes padding after ENDLIB is common, anything else is garbage
+fn check_trailing_data<R: Read>(
+    reader: &mut RecordReader<R>,
+    options: &ReadOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<(), GdsError> {
+    if let Some(offset) = reader.trailing_data_offset()? {
+        let err = GdsError::InvalidRecordData("garbage data after ENDLIB".to_string()).in_context(
+            ErrorContext {
+                offset,
+                record_index: reader.record_index() + 1,
+                ..Default::default()
+            },
+        );
+        if options.strict {
+            return Err(err);
         }
-        record_ranges.push((idx, idx + record_len));
-        idx += record_len;
+        diagnostics.push(Diagnostic::from_error(Severity::Warning, &err));
     }
+    Ok(())
+}
 
-    let gds_records: Vec<gds_record::Record> = record_ranges
-        .par_iter()
-        .map(|range| to_gds_record(&buff, range).unwrap())
-        .collect();
-
-    if gds_records.len() == 0 {
-        return Result::Err(Box::new(gds_err!(
-            "not valid gds file, no any valid records found"
-        )));
-    }
+/// write gds lib to gds file
+pub fn write_gdsii<T: AsRef<path::Path>>(
+    gds_file: T,
+    lib: &gds_model::Lib,
+    options: &WriteOptions,
+) -> Result<(), GdsError> {
+    write_gdsii_to(BufWriter::new(File::create(gds_file)?), lib, options)
+}
 
-    // transfer gds record data to gds object
-    Ok(gds_parser::parse_gds(gds_records)?)
-    // Ok(Box::new(gds_model::Lib::new("")))
+/// write gds lib to any byte stream, compressed if `options.compression` is set
+pub fn write_gdsii_to<W: Write>(
+    mut writer: W,
+    lib: &gds_model::Lib,
+    options: &WriteOptions,
+) -> Result<(), GdsError> {
+    lib.write_to(&mut writer, options)
 }